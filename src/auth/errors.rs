@@ -13,6 +13,7 @@ pub enum AuthError {
     JsonEncode(rustc_serialize::json::EncoderError),
     JsonDecode(rustc_serialize::json::ParserError),
     JsonContent(String),
+    Unauthorized(String),
     Fail(String)
 }
 
@@ -23,6 +24,7 @@ impl fmt::Display for AuthError {
             AuthError::JsonEncode(ref err) => write!(f, "Auth JSON Encode error: {}", err),
             AuthError::JsonDecode(ref err) => write!(f, "Auth JSON Decode error: {}", err),
             AuthError::JsonContent(ref s) => write!(f, "Auth JSON Content error: {}", s),
+            AuthError::Unauthorized(ref s) => write!(f, "Auth token rejected: {}", s),
             AuthError::Fail(ref s) => write!(f, "Auth Fail: {}", s),
         }
     }
@@ -35,6 +37,7 @@ impl error::Error for AuthError {
             AuthError::JsonEncode(ref err) => err.description(),
             AuthError::JsonDecode(ref err) => err.description(),
             AuthError::JsonContent(ref s) => s,
+            AuthError::Unauthorized(ref s) => s,
             AuthError::Fail(ref s) => s,
         }
     }
@@ -45,6 +48,7 @@ impl error::Error for AuthError {
             AuthError::JsonEncode(ref err) => Some(err),
             AuthError::JsonDecode(ref err) => Some(err),
             AuthError::JsonContent(_) => None,
+            AuthError::Unauthorized(_) => None,
             AuthError::Fail(_) => None,
         }
     }
@@ -5,12 +5,14 @@ use hyper::client::IntoUrl;
 use hyper::header::{Headers, ContentType};
 use hyper::method::Method;
 use reqwest::Client;
-use reqwest::RequestBuilder;
+use reqwest::{RequestBuilder, Response};
 use rustc_serialize::{Encodable, json};
 use std::clone::Clone;
+use std::cmp;
 use std::io::Read;
 use std::option::Option;
 use std::result::Result;
+use std::str::FromStr;
 use std::sync::Mutex;
 
 use auth::errors::AuthError;
@@ -21,33 +23,122 @@ use auth::errors::AuthError;
 pub trait Auth {
     fn build_request(&self, m: Method, path: String, headers: Headers)
         -> Result<RequestBuilder, AuthError>;
+
+    /*
+     * As build_request, also returning the token the request was built
+     * with, so that it can be passed to invalidate_token if it's rejected
+     */
+    fn build_request_with_token(&self, m: Method, path: String, headers: Headers)
+            -> Result<(RequestBuilder, Option<String>), AuthError> {
+        self.build_request(m, path, headers).map(|r| (r, None))
+    }
+
+    /*
+     * Called when the server rejects a token. Returns true if the next
+     * build_request will use a new token, or false if there's no way to
+     * get one. If the current token isn't the rejected one, it's kept,
+     * since another thread has already replaced the rejected one.
+     */
+    fn invalidate_token(&self, _rejected: &str) -> bool {
+        false
+    }
+
+    /*
+     * Details of the current session, authenticating first if necessary.
+     * Implementations that don't override this can't be used for anything
+     * that needs the storage URL up front (temp URLs, /info and so on);
+     * SessionInfo::new covers those that only have a storage URL.
+     */
+    fn session_info(&self) -> Result<SessionInfo, AuthError> {
+        let err_msg = String::from("Session details are not available for this auth type");
+        Err(AuthError::Fail(err_msg))
+    }
+}
+
+/*
+ * Which of the catalog endpoints to use for the object store
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interface {
+    Public,
+    Internal,
+    Admin
+}
+
+impl Interface {
+    // The key holding the endpoint URL in the v2 catalog
+    fn v2_key(&self) -> &'static str {
+        match *self {
+            Interface::Public => "publicURL",
+            Interface::Internal => "internalURL",
+            Interface::Admin => "adminURL"
+        }
+    }
+
+    // The value of the "interface" field in the v3 catalog
+    fn v3_name(&self) -> &'static str {
+        match *self {
+            Interface::Public => "public",
+            Interface::Internal => "internal",
+            Interface::Admin => "admin"
+        }
+    }
+}
+
+impl FromStr for Interface {
+    type Err = AuthError;
+
+    fn from_str(s: &str) -> Result<Interface, AuthError> {
+        match s {
+            "public" | "publicURL" => Ok(Interface::Public),
+            "internal" | "internalURL" => Ok(Interface::Internal),
+            "admin" | "adminURL" => Ok(Interface::Admin),
+            _ => {
+                let err_msg = format!("Unknown endpoint interface: {}", s);
+                Err(AuthError::Fail(err_msg))
+            }
+        }
+    }
 }
 
 /*
  * Helper methods for manipulating JSON objects
  */
 
-fn post_json<T>(client: &Client, url: &str, payload: &T)
-        -> Result<String, AuthError> where T: Encodable {
-    // POSTs an encodable payload to a given URL
+fn post_json_response<T>(client: &Client, url: &str, payload: &T)
+        -> Result<Response, AuthError> where T: Encodable {
+    // POSTs an encodable payload to a given URL, returning the raw response
     let body: String = match json::encode(payload) {
         Ok(s) => s,
         Err(e) => return Err(AuthError::JsonEncode(e))
     };
     let mut headers = Headers::new();
     headers.set(ContentType::json());
-    let post_res = client.post(url).body(&body[..]).headers(headers).send();
-    match post_res {
-        Ok(_) => {
-            let mut res = post_res.unwrap();
-            let mut _b = String::new();
-            res.read_to_string(&mut _b).unwrap();
-            Ok(_b)
-        },
+    match client.post(url).body(&body[..]).headers(headers).send() {
+        Ok(res) => Ok(res),
         Err(e) => Err(AuthError::Http(e))
     }
 }
 
+fn read_body(res: &mut Response) -> Result<String, AuthError> {
+    let mut _b = String::new();
+    match res.read_to_string(&mut _b) {
+        Ok(_) => Ok(_b),
+        Err(e) => {
+            error!("Failed to read auth response body: {}", e);
+            let err_msg = format!("Failed to read auth response body: {}", e);
+            Err(AuthError::Fail(err_msg))
+        }
+    }
+}
+
+fn post_json<T>(client: &Client, url: &str, payload: &T)
+        -> Result<String, AuthError> where T: Encodable {
+    // POSTs an encodable payload to a given URL
+    let mut res = try!(post_json_response(client, url, payload));
+    read_body(&mut res)
+}
+
 fn find_err<'j>(obj: &'j json::Json, key: &'j str) -> Result<&'j json::Json, AuthError> {
     match obj.as_object() {
         Some(_) => {
@@ -78,6 +169,541 @@ fn as_string<'j>(obj: &'j json::Json) -> Option<String> {
     }
 }
 
+fn json_object(pairs: Vec<(&str, json::Json)>) -> json::Json {
+    let mut obj = json::Object::new();
+    for (k, v) in pairs {
+        obj.insert(String::from(k), v);
+    }
+    json::Json::Object(obj)
+}
+
+fn parse_expiry(expires: &json::Json) -> Result<DateTime<UTC>, AuthError> {
+    match expires.as_string() {
+        Some(s) => {
+            match s.parse::<DateTime<UTC>>() {
+                Ok(d) => Ok(d),
+                _ => {
+                    error!("Failed to parse auth token expiry time");
+                    let err_msg = String::from("Failed to parse auth token expiry time");
+                    Err(AuthError::JsonContent(err_msg))
+                }
+            }
+        },
+        _ => {
+            error!("Failed to parse auth token expiry time");
+            let err_msg = String::from("Failed to parse auth token expiry time");
+            Err(AuthError::JsonContent(err_msg))
+        }
+    }
+}
+
+/*
+ * Token state shared by all of the session types
+ */
+
+#[derive(Clone)]
+struct SessionToken {
+    token: Option<String>,
+    storage_url: Option<String>,
+    expires: Option<DateTime<UTC>>,
+    // When the current token was requested
+    issued: Option<DateTime<UTC>>,
+    region: Option<String>,
+    project_id: Option<String>,
+    project_name: Option<String>,
+    catalog: ServiceCatalog
+}
+
+impl SessionToken {
+    fn new () -> SessionToken {
+        SessionToken{
+            token: None,
+            storage_url: None,
+            expires: None,
+            issued: None,
+            region: None,
+            project_id: None,
+            project_name: None,
+            catalog: ServiceCatalog::new()
+        }
+    }
+
+    /*
+     * Store the endpoint we picked from the catalogue and the project
+     * the token is scoped to
+     */
+    fn set_catalog(&mut self, catalog: ServiceCatalog, endpoint: CatalogEndpoint,
+                   project: Option<&json::Json>) {
+        self.storage_url = Some(endpoint.url);
+        self.region = endpoint.region;
+        self.project_id = project.and_then(|p| optional_string(p, "id"));
+        self.project_name = project.and_then(|p| optional_string(p, "name"));
+        self.catalog = catalog;
+    }
+
+    /*
+     * Tokens are refreshed an hour before they expire, or a quarter of
+     * their lifetime before for shorter lived ones. A token without an
+     * expiry time is used until the server rejects it.
+     */
+    fn is_valid(&self) -> bool {
+        if self.token.is_none() {
+            return false
+        }
+        match self.expires {
+            Some(expires) => {
+                let margin = match self.issued {
+                    Some(issued) => cmp::min(Duration::hours(1), (expires - issued) / 4),
+                    None => Duration::hours(1)
+                };
+                UTC::now() < expires - margin
+            },
+            None => true
+        }
+    }
+}
+
+/*
+ * A read-only snapshot of the current state of an auth session
+ */
+#[derive(Clone, Debug)]
+pub struct SessionInfo {
+    storage_url: String,
+    expires: Option<DateTime<UTC>>,
+    region: Option<String>,
+    project_id: Option<String>,
+    project_name: Option<String>,
+    catalog: ServiceCatalog
+}
+
+impl SessionInfo {
+    // A session with just a storage URL, and an empty catalogue
+    pub fn new(storage_url: String) -> SessionInfo {
+        SessionInfo {
+            storage_url: storage_url,
+            expires: None,
+            region: None,
+            project_id: None,
+            project_name: None,
+            catalog: ServiceCatalog::new()
+        }
+    }
+
+    pub fn storage_url(&self) -> &str {
+        &self.storage_url
+    }
+
+    pub fn expires(&self) -> Option<DateTime<UTC>> {
+        self.expires
+    }
+
+    // The region of the endpoint that was picked from the catalogue
+    pub fn region(&self) -> Option<&str> {
+        self.region.as_ref().map(|r| &r[..])
+    }
+
+    pub fn project_id(&self) -> Option<&str> {
+        self.project_id.as_ref().map(|p| &p[..])
+    }
+
+    pub fn project_name(&self) -> Option<&str> {
+        self.project_name.as_ref().map(|p| &p[..])
+    }
+
+    // The full catalogue (empty for auth types that don't have one)
+    pub fn catalog(&self) -> &ServiceCatalog {
+        &self.catalog
+    }
+}
+
+fn snapshot_session(session_token: SessionToken) -> Result<SessionInfo, AuthError> {
+    let storage_url = match session_token.storage_url {
+        Some(u) => u,
+        None => {
+            error!("No storage base URL found");
+            let err_msg = String::from("No storage base URL found");
+            return Err(AuthError::Fail(err_msg))
+        }
+    };
+    Ok(SessionInfo {
+        storage_url: storage_url,
+        expires: session_token.expires,
+        region: session_token.region,
+        project_id: session_token.project_id,
+        project_name: session_token.project_name,
+        catalog: session_token.catalog
+    })
+}
+
+/*
+ * Make sure the token held in the mutex is valid, calling the supplied
+ * authenticate function if it isn't, and return a copy of it. Other
+ * threads block until authentication is complete, then see the new token.
+ */
+fn get_token<F>(token_lock: &Mutex<SessionToken>, authenticate: F)
+        -> Result<SessionToken, AuthError>
+        where F: FnOnce(&mut SessionToken) -> Result<(), AuthError> {
+    let mut session_token = match token_lock.lock() {
+        Ok(t) => t,
+        Err(_) => {
+            error!("Failed to grab the current access token");
+            let err_msg = String::from("Locking token failed");
+            return Err(AuthError::Fail(err_msg))
+        }
+    };
+    if !session_token.is_valid() {
+        let issued = UTC::now();
+        try!(authenticate(&mut session_token));
+        session_token.issued = Some(issued);
+    }
+    Ok(session_token.clone())
+}
+
+/*
+ * Throw away the current token, so the next request has to authenticate,
+ * unless it has already been replaced
+ */
+fn invalidate_token(token_lock: &Mutex<SessionToken>, rejected: &str) -> bool {
+    match token_lock.lock() {
+        Ok(mut session_token) => {
+            if session_token.token.as_ref().map(|t| &t[..]) == Some(rejected) {
+                session_token.token = None;
+                session_token.expires = None;
+            } else {
+                debug!("Rejected token has already been replaced");
+            }
+            true
+        },
+        Err(_) => {
+            error!("Failed to lock the current access token");
+            false
+        }
+    }
+}
+
+/*
+ * Get auth token, authenticating if necessary
+ */
+header! { (XAuthToken, "X-Auth-Token") => [String] }
+
+fn build_token_request(client: &Client, session_token: SessionToken,
+                       m: Method, path: String, headers: Headers)
+        -> Result<(RequestBuilder, Option<String>), AuthError> {
+    let token = match session_token.token {
+        Some(t) => t,
+        None => {
+            error!("No current access token found");
+            let err_msg = String::from("No current access token found");
+            return Err(AuthError::Fail(err_msg))
+        }
+    };
+    let storage_base_url: &String = match session_token.storage_url {
+        Some(ref u) => u,
+        None => {
+            error!("No storage base URL found");
+            let err_msg = String::from("No storage base URL found");
+            return Err(AuthError::Fail(err_msg))
+        }
+    };
+    let req = try!(token_request(client, storage_base_url, token.clone(), m, path, headers));
+    Ok((req, Some(token)))
+}
+
+fn token_request(client: &Client, storage_base_url: &String, token: String,
+                 m: Method, path: String, mut headers: Headers)
+        -> Result<RequestBuilder, AuthError> {
+    let mut url = String::from("");
+    url.push_str(storage_base_url);
+    url.push_str(&path);
+    debug!("Request base URL: {}", url);
+    match url.into_url() {
+        Ok(_u) => {
+            headers.set(XAuthToken(token));
+            Ok(client.request(m, _u).headers(headers))
+        }
+        _ => {
+            error!("Failed to parse request base URL: {}", url);
+            let err_msg = String::from("Failed to parse base request URL");
+            Err(AuthError::Fail(err_msg))
+        }
+    }
+}
+
+/*
+ * The parsed service catalogue, as returned by Keystone
+ */
+
+#[derive(Clone, Debug)]
+pub struct CatalogEndpoint {
+    region: Option<String>,
+    interface: Interface,
+    url: String
+}
+
+impl CatalogEndpoint {
+    pub fn region(&self) -> Option<&str> {
+        self.region.as_ref().map(|r| &r[..])
+    }
+
+    pub fn interface(&self) -> Interface {
+        self.interface
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CatalogService {
+    service_type: String,
+    name: Option<String>,
+    endpoints: Vec<CatalogEndpoint>
+}
+
+impl CatalogService {
+    pub fn service_type(&self) -> &str {
+        &self.service_type
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_ref().map(|n| &n[..])
+    }
+
+    pub fn endpoints(&self) -> &[CatalogEndpoint] {
+        &self.endpoints
+    }
+
+    /*
+     * Endpoints in the given region and with the given interface, where
+     * None matches anything
+     */
+    pub fn find_endpoints(&self, region: Option<&str>, interface: Option<Interface>)
+            -> Vec<&CatalogEndpoint> {
+        self.endpoints.iter().filter(|e| {
+            let region_matches = match region {
+                Some(r) => e.region() == Some(r),
+                None => true
+            };
+            let interface_matches = match interface {
+                Some(i) => e.interface == i,
+                None => true
+            };
+            region_matches && interface_matches
+        }).collect()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ServiceCatalog {
+    services: Vec<CatalogService>
+}
+
+impl ServiceCatalog {
+    fn new () -> ServiceCatalog {
+        ServiceCatalog {
+            services: Vec::new()
+        }
+    }
+
+    pub fn services(&self) -> &[CatalogService] {
+        &self.services
+    }
+
+    pub fn services_of_type(&self, service_type: &str) -> Vec<&CatalogService> {
+        self.services.iter().filter(|s| s.service_type == service_type).collect()
+    }
+}
+
+fn required_string(obj: &json::Json, key: &str) -> Result<String, AuthError> {
+    match as_string(try!(find_err(obj, key))) {
+        Some(s) => Ok(s),
+        None => {
+            error!("Expected a string for key {}", key);
+            let err_msg = format!("Expected a string for key: {}", key);
+            Err(AuthError::JsonContent(err_msg))
+        }
+    }
+}
+
+fn optional_string(obj: &json::Json, key: &str) -> Option<String> {
+    obj.find(key).and_then(|x| x.as_string()).map(String::from)
+}
+
+/*
+ * Parse a catalogue, given a function to parse the endpoints for each
+ * service (the v2 and v3 endpoint formats differ)
+ */
+fn parse_catalogue<F>(catalogue: &json::Json, parse_endpoint: F)
+        -> Result<ServiceCatalog, AuthError>
+        where F: Fn(&json::Json, &mut Vec<CatalogEndpoint>) {
+    let catalogue_array = match catalogue.as_array() {
+        Some(a) => a,
+        None => {
+            error!("No service catalogue found");
+            let err_msg = String::from("No service catalogue found");
+            return Err(AuthError::JsonContent(err_msg))
+        }
+    };
+    let mut services = Vec::new();
+    for service in catalogue_array {
+        let mut endpoints = Vec::new();
+        match try!(find_err(service, "endpoints")).as_array() {
+            Some(endpoints_array) => {
+                for endpoint in endpoints_array {
+                    parse_endpoint(endpoint, &mut endpoints);
+                }
+            },
+            None => ()
+        };
+        services.push(CatalogService {
+            service_type: try!(required_string(service, "type")),
+            name: optional_string(service, "name"),
+            endpoints: endpoints
+        });
+    }
+    Ok(ServiceCatalog {
+        services: services
+    })
+}
+
+/*
+ * v2 endpoints list the URL for each interface in a single entry
+ */
+fn parse_endpoint_v2(endpoint: &json::Json, endpoints: &mut Vec<CatalogEndpoint>) {
+    let region = optional_string(endpoint, "region");
+    for interface in &[Interface::Public, Interface::Internal, Interface::Admin] {
+        if let Some(url) = optional_string(endpoint, interface.v2_key()) {
+            endpoints.push(CatalogEndpoint {
+                region: region.clone(),
+                interface: *interface,
+                url: url
+            });
+        }
+    }
+}
+
+/*
+ * v3 endpoints are listed once per interface, with the region given
+ * as "region_id" (and, in older deployments, "region")
+ */
+fn parse_endpoint_v3(endpoint: &json::Json, endpoints: &mut Vec<CatalogEndpoint>) {
+    let interface = match optional_string(endpoint, "interface") {
+        Some(i) => match i.parse::<Interface>() {
+            Ok(i) => i,
+            Err(_) => return
+        },
+        None => return
+    };
+    let url = match optional_string(endpoint, "url") {
+        Some(u) => u,
+        None => return
+    };
+    let region = optional_string(endpoint, "region_id").or_else(
+        || optional_string(endpoint, "region"));
+    endpoints.push(CatalogEndpoint {
+        region: region,
+        interface: interface,
+        url: url
+    });
+}
+
+/*
+ * How to pick the object store endpoint out of a service catalogue
+ */
+
+pub struct EndpointFilter {
+    region: Option<String>,
+    interface: Interface,
+    service_type: String,
+    service_name: Option<String>,
+    storage_url: Option<String>
+}
+
+impl EndpointFilter {
+    fn new (region: Option<String>, interface: Interface) -> EndpointFilter {
+        EndpointFilter {
+            region: region,
+            interface: interface,
+            service_type: String::from("object-store"),
+            service_name: None,
+            storage_url: None
+        }
+    }
+
+    fn matches_service(&self, service: &CatalogService) -> bool {
+        if service.service_type != self.service_type {
+            return false
+        }
+        match self.service_name {
+            Some(ref n) => service.name() == Some(&n[..]),
+            None => true
+        }
+    }
+
+    /*
+     * Find the endpoint for the last matching service in the catalogue
+     * (the first matching endpoint of that service), unless the storage
+     * URL has been overridden
+     */
+    fn select(&self, catalog: &ServiceCatalog) -> Result<CatalogEndpoint, AuthError> {
+        if let Some(ref u) = self.storage_url {
+            debug!("Using storage URL override: {}", u);
+            return Ok(CatalogEndpoint {
+                region: self.region.clone(),
+                interface: self.interface,
+                url: u.clone()
+            })
+        }
+        let region = self.region.as_ref().map(|r| &r[..]);
+        let mut found_service = false;
+        for service in catalog.services.iter().rev().filter(|s| self.matches_service(s)) {
+            found_service = true;
+            // Another service of the same type may still match
+            match service.find_endpoints(region, Some(self.interface)).first() {
+                Some(e) => return Ok((*e).clone()),
+                None => ()
+            };
+        }
+        let err_msg = match (found_service, region) {
+            (true, Some(r)) => format!("No region matching '{}' located", r),
+            (true, None) => String::from("No endpoint for storage-url found"),
+            (false, _) => format!("Failed to find {} in catalogue", self.service_type)
+        };
+        error!("{}", err_msg);
+        Err(AuthError::JsonContent(err_msg))
+    }
+}
+
+/*
+ * Options for picking the object store out of the service catalogue,
+ * shared by the Keystone session types
+ */
+pub trait CatalogAuth: Sized {
+    fn endpoint_filter_mut(&mut self) -> &mut EndpointFilter;
+
+    /*
+     * Pick the object store by service name, when the catalogue lists more
+     * than one service of the same type
+     */
+    fn with_service_name(mut self, service_name: String) -> Self {
+        self.endpoint_filter_mut().service_name = Some(service_name);
+        self
+    }
+
+    // Look for a service type other than "object-store"
+    fn with_service_type(mut self, service_type: String) -> Self {
+        self.endpoint_filter_mut().service_type = service_type;
+        self
+    }
+
+    // Use this storage URL instead of the one in the catalogue
+    fn with_storage_url(mut self, storage_url: String) -> Self {
+        self.endpoint_filter_mut().storage_url = Some(storage_url);
+        self
+    }
+}
+
 /*
  *  Keystone Auth V2
  */
@@ -99,31 +725,14 @@ struct AuthRequestV2<'s> {
     auth: AuthRequestAuthV2<'s>,
 }
 
-#[derive(Clone)]
-struct KeystoneAuthV2Token {
-    token: Option<String>,
-    storage_url: Option<String>,
-    expires: Option<DateTime<UTC>>
-}
-
-impl KeystoneAuthV2Token {
-    fn new () -> KeystoneAuthV2Token {
-        KeystoneAuthV2Token{
-            token: None,
-            storage_url: None,
-            expires: None
-        }
-    }
-}
-
 pub struct KeystoneAuthV2 {
     username: String,
     password: String,
     tenant: String,
     auth_url: String,
-    region: Option<String>,
+    filter: EndpointFilter,
     client: Client,
-    token: Mutex<KeystoneAuthV2Token>,
+    token: Mutex<SessionToken>,
 }
 
 unsafe impl Send for KeystoneAuthV2 {}
@@ -131,68 +740,25 @@ unsafe impl Sync for KeystoneAuthV2 {}
 
 impl KeystoneAuthV2 {
     pub fn new (username: String, password: String, tenant: String,
-                auth_url: String, region: Option<String>) -> KeystoneAuthV2 {
+                auth_url: String, region: Option<String>,
+                interface: Interface) -> KeystoneAuthV2 {
         let client = Client::new().unwrap();
-        let token = KeystoneAuthV2Token::new();
+        let token = SessionToken::new();
         KeystoneAuthV2 {
             username: username,
             password: password,
             tenant: tenant,
             auth_url: auth_url,
-            region: region,
+            filter: EndpointFilter::new(region, interface),
             client: client,
             token: Mutex::new(token)
         }
     }
 
-    fn get_endpoint(self: &KeystoneAuthV2, endpoints: &json::Json) -> Result<Option<String>, AuthError> {
-        match endpoints.as_array() {
-            Some(endpoints_array) => {
-                match self.region {
-                    Some(ref r) => {
-                        'eps: for endpoint in endpoints_array {
-                            let _r = {
-                                if let Ok(x) = find_err(&endpoint, "region") {x}
-                                else {continue 'eps}
-                            };
-                            let _fr = {
-                                if let Some(x) = as_string(&_r) {x}
-                                else {continue 'eps}
-                            };
-                            if &_fr == r {
-                                let _public_url = try!(find_err(&endpoint, "publicURL"));
-                                let storage_url = as_string(&_public_url);
-                                return Ok(storage_url)
-                            }
-                        }
-                        error!("No region matching '{}' located", r);
-                        let err_msg = format!("No region matching '{}' located", r);
-                        return Err(AuthError::JsonContent(err_msg))
-                    },
-                    None => {
-                        for endpoint in endpoints_array {
-                            let _public_url = try!(find_err(&endpoint, "publicURL"));
-                            let storage_url = as_string(&_public_url);
-                            return Ok(storage_url)
-                        }
-                        error!("No endpoint for storage-url found");
-                        let err_msg = format!("No endpoint for storage-url found");
-                        return Err(AuthError::JsonContent(err_msg))
-                    }
-                }
-            },
-            None => {
-                error!("No endpoints found");
-                let err_msg = String::from("No endpoints found");
-                return Err(AuthError::JsonContent(err_msg))
-            }
-        }
-    }
-
     /*
      * Authenticate using supplied parameters
      */
-    fn authenticate(&self, keystone_token: &mut KeystoneAuthV2Token) -> Result<(), AuthError> {
+    fn authenticate(&self, session_token: &mut SessionToken) -> Result<(), AuthError> {
         debug!("Starting authentication");
         let auth = AuthRequestV2 {
             auth: AuthRequestAuthV2 {
@@ -203,7 +769,7 @@ impl KeystoneAuthV2 {
             tenantName: &self.tenant
         }};
 
-        let _au = &format!("{}/{}", &self.auth_url, "tokens")[..];
+        let _au = &format!("{}/{}", self.auth_url.trim_right_matches('/'), "tokens")[..];
         let response = try!(post_json(&self.client, _au, &auth));
         let response_object: json::Json = match json::Json::from_str(&response) {
             Ok(j) => j,
@@ -218,139 +784,675 @@ impl KeystoneAuthV2 {
 
         // Get the service catalogue and find the object store
         let catalogue: &json::Json = try!(find_err(access, "serviceCatalog"));
-        let mut storage_url: Option<String> = None;
-
-        match catalogue.as_array() {
-            Some(catalogue_array) => {
-                'catalogue: for service in catalogue_array {
-                    let _type = try!(find_err(service, "type"));
-                    match _type.as_string() {
-                        Some("object-store") => {
-                            let endpoints = try!(find_err(&service, "endpoints"));
-                            storage_url = try!(self.get_endpoint(&endpoints));
-                        },
-                        _ => ()
-                    };
-                }
-            },
-            None => ()
-        };
-        match storage_url {
-            Some(_) => {
-                keystone_token.storage_url = storage_url;
-                keystone_token.token = as_string(&token_id);
-                match expires.as_string() {
-                    Some(s) => {
-                        match s.parse::<DateTime<UTC>>() {
-                            Ok(d) => keystone_token.expires = Some(d),
-                            _ => {
-                                error!("Failed to parse auth token expiry time");
-                                let err_msg = String::from("Failed to parse auth token expiry time");
-                                return Err(AuthError::JsonContent(err_msg))
-                            }
-                        };
-                    },
-                    _ => {
-                        error!("Failed to parse auth token expiry time");
-                        let err_msg = String::from("Failed to parse auth token expiry time");
-                        return Err(AuthError::JsonContent(err_msg))
-                    }
-                };
-                Ok(())
-            },
-            _ => {
-                error!("Failed to find object-store in catalogue");
-                let err_msg = String::from("Failed to find object-store in catalogue");
-                Err(AuthError::JsonContent(err_msg))
-            }
-        }
+        let catalog = try!(parse_catalogue(catalogue, parse_endpoint_v2));
+        let endpoint = try!(self.filter.select(&catalog));
+
+        session_token.set_catalog(catalog, endpoint, token.find("tenant"));
+        session_token.token = as_string(&token_id);
+        session_token.expires = Some(try!(parse_expiry(expires)));
+        Ok(())
     }
+}
 
-    unsafe fn get_token(&self) -> Result<(), AuthError> {
-        {
-            match self.token.try_lock() {
-                Ok(mut keystone_token) => {
-                    match keystone_token.token {
-                        Some(_) => {
-                            match keystone_token.expires {
-                                Some(datetime) => {
-                                    let now = UTC::now();
-                                    let d: DateTime<UTC> = datetime - Duration::hours(1);
-                                    if now.lt(&d) {    // If the token expires more than
-                                        return Ok(())  // an hour from now, just return it
-                                    }
-                                },
-                                None => ()  // No expiry time means no token
-                            };
-                        },
-                        None => ()  // No token means we need to auth
-                    };
-                    // If we get here then we have a lock but no valid token, so auth
-                    return self.authenticate(&mut keystone_token)
-                },
-                Err(_) => return Ok(())  // If we can't get the lock, just assume that
-                                         // another thread is authenticating - build_request
-                                         // will then lock until it's complete
-            }
-        };
+impl CatalogAuth for KeystoneAuthV2 {
+    fn endpoint_filter_mut(&mut self) -> &mut EndpointFilter {
+        &mut self.filter
+    }
+}
+
+impl Auth for KeystoneAuthV2 {
+    fn build_request(&self, m: Method, path: String, headers: Headers)
+            -> Result<RequestBuilder, AuthError> {
+        self.build_request_with_token(m, path, headers).map(|(r, _)| r)
+    }
+
+    fn build_request_with_token(&self, m: Method, path: String, headers: Headers)
+            -> Result<(RequestBuilder, Option<String>), AuthError> {
+        // Make sure we have a valid auth token
+        let session_token = try!(get_token(&self.token, |t| self.authenticate(t)));
+        build_token_request(&self.client, session_token, m, path, headers)
+    }
+
+    fn invalidate_token(&self, rejected: &str) -> bool {
+        invalidate_token(&self.token, rejected)
+    }
+
+    fn session_info(&self) -> Result<SessionInfo, AuthError> {
+        let session_token = try!(get_token(&self.token, |t| self.authenticate(t)));
+        snapshot_session(session_token)
     }
 }
 
 /*
- * Get auth token, authenticating if necessary
+ *  Keystone Auth V3
  */
-header! { (XAuthToken, "X-Auth-Token") => [String] }
 
-impl Auth for KeystoneAuthV2 {
-    fn build_request(&self, m: Method, path: String, mut headers: Headers)
+header! { (XSubjectToken, "X-Subject-Token") => [String] }
+
+pub struct KeystoneAuthV3 {
+    username: String,
+    password: String,
+    user_domain: String,
+    project: String,
+    project_domain: String,
+    auth_url: String,
+    filter: EndpointFilter,
+    client: Client,
+    token: Mutex<SessionToken>,
+}
+
+unsafe impl Send for KeystoneAuthV3 {}
+unsafe impl Sync for KeystoneAuthV3 {}
+
+impl KeystoneAuthV3 {
+    pub fn new (username: String, password: String, user_domain: String,
+                project: String, project_domain: String, auth_url: String,
+                region: Option<String>, interface: Interface) -> KeystoneAuthV3 {
+        let client = Client::new().unwrap();
+        let token = SessionToken::new();
+        KeystoneAuthV3 {
+            username: username,
+            password: password,
+            user_domain: user_domain,
+            project: project,
+            project_domain: project_domain,
+            auth_url: auth_url,
+            filter: EndpointFilter::new(region, interface),
+            client: client,
+            token: Mutex::new(token)
+        }
+    }
+
+    /*
+     * Build the password identity and project scope for a token request
+     */
+    fn auth_payload(&self) -> json::Json {
+        let user_domain = json_object(vec![
+            ("name", json::Json::String(self.user_domain.clone()))
+        ]);
+        let user = json_object(vec![
+            ("name", json::Json::String(self.username.clone())),
+            ("domain", user_domain),
+            ("password", json::Json::String(self.password.clone()))
+        ]);
+        let identity = json_object(vec![
+            ("methods", json::Json::Array(vec![
+                json::Json::String(String::from("password"))])),
+            ("password", json_object(vec![("user", user)]))
+        ]);
+        let project_domain = json_object(vec![
+            ("name", json::Json::String(self.project_domain.clone()))
+        ]);
+        let scope = json_object(vec![
+            ("project", json_object(vec![
+                ("name", json::Json::String(self.project.clone())),
+                ("domain", project_domain)
+            ]))
+        ]);
+        json_object(vec![
+            ("auth", json_object(vec![
+                ("identity", identity),
+                ("scope", scope)
+            ]))
+        ])
+    }
+
+    /*
+     * Authenticate using supplied parameters
+     */
+    fn authenticate(&self, session_token: &mut SessionToken) -> Result<(), AuthError> {
+        debug!("Starting v3 authentication");
+        let payload = self.auth_payload();
+        authenticate_v3(&self.client, &self.auth_url, &payload,
+                        &self.filter, session_token)
+    }
+}
+
+impl CatalogAuth for KeystoneAuthV3 {
+    fn endpoint_filter_mut(&mut self) -> &mut EndpointFilter {
+        &mut self.filter
+    }
+}
+
+impl Auth for KeystoneAuthV3 {
+    fn build_request(&self, m: Method, path: String, headers: Headers)
             -> Result<RequestBuilder, AuthError> {
+        self.build_request_with_token(m, path, headers).map(|(r, _)| r)
+    }
+
+    fn build_request_with_token(&self, m: Method, path: String, headers: Headers)
+            -> Result<(RequestBuilder, Option<String>), AuthError> {
         // Make sure we have a valid auth token
-        unsafe {
-            match self.get_token() {
-                Ok(()) => (),
-                Err(e) => return Err(e)
-            }
+        let session_token = try!(get_token(&self.token, |t| self.authenticate(t)));
+        build_token_request(&self.client, session_token, m, path, headers)
+    }
+
+    fn invalidate_token(&self, rejected: &str) -> bool {
+        invalidate_token(&self.token, rejected)
+    }
+
+    fn session_info(&self) -> Result<SessionInfo, AuthError> {
+        let session_token = try!(get_token(&self.token, |t| self.authenticate(t)));
+        snapshot_session(session_token)
+    }
+}
+
+/*
+ *  Keystone Auth V3 with an application credential
+ */
+
+enum ApplicationCredential {
+    Id(String),
+    Name { name: String, username: String, user_domain: String }
+}
+
+pub struct KeystoneAuthV3AppCredential {
+    credential: ApplicationCredential,
+    secret: String,
+    auth_url: String,
+    filter: EndpointFilter,
+    client: Client,
+    token: Mutex<SessionToken>,
+}
+
+unsafe impl Send for KeystoneAuthV3AppCredential {}
+unsafe impl Sync for KeystoneAuthV3AppCredential {}
+
+impl KeystoneAuthV3AppCredential {
+    pub fn new_with_id (id: String, secret: String, auth_url: String,
+                        region: Option<String>,
+                        interface: Interface) -> KeystoneAuthV3AppCredential {
+        KeystoneAuthV3AppCredential::create(
+            ApplicationCredential::Id(id), secret, auth_url, region, interface)
+    }
+
+    pub fn new_with_name (name: String, username: String, user_domain: String,
+                          secret: String, auth_url: String,
+                          region: Option<String>,
+                          interface: Interface) -> KeystoneAuthV3AppCredential {
+        let credential = ApplicationCredential::Name {
+            name: name,
+            username: username,
+            user_domain: user_domain
         };
-        // Either the current thread got the token and it's ready, or
-        // another thread is getting the token and we have to wait
-        let keystone_token: KeystoneAuthV2Token = match self.token.lock() {
-            Ok(t) => t.clone(),
-            Err(_) => {
-                error!("Failed to grab the current access token");
-                let err_msg = String::from("Locking token failed");
-                return Err(AuthError::Fail(err_msg))
+        KeystoneAuthV3AppCredential::create(
+            credential, secret, auth_url, region, interface)
+    }
+
+    fn create (credential: ApplicationCredential, secret: String,
+               auth_url: String, region: Option<String>,
+               interface: Interface) -> KeystoneAuthV3AppCredential {
+        let client = Client::new().unwrap();
+        let token = SessionToken::new();
+        KeystoneAuthV3AppCredential {
+            credential: credential,
+            secret: secret,
+            auth_url: auth_url,
+            filter: EndpointFilter::new(region, interface),
+            client: client,
+            token: Mutex::new(token)
+        }
+    }
+
+    /*
+     * Build the application credential identity for a token request. The
+     * credential is already scoped, so no scope is sent.
+     */
+    fn auth_payload(&self) -> json::Json {
+        let mut credential = match self.credential {
+            ApplicationCredential::Id(ref id) => vec![
+                ("id", json::Json::String(id.clone()))
+            ],
+            ApplicationCredential::Name { ref name, ref username, ref user_domain } => {
+                let user = json_object(vec![
+                    ("name", json::Json::String(username.clone())),
+                    ("domain", json_object(vec![
+                        ("name", json::Json::String(user_domain.clone()))
+                    ]))
+                ]);
+                vec![
+                    ("name", json::Json::String(name.clone())),
+                    ("user", user)
+                ]
             }
         };
-        let token = match keystone_token.token {
-            Some(t) => t,
+        credential.push(("secret", json::Json::String(self.secret.clone())));
+        let identity = json_object(vec![
+            ("methods", json::Json::Array(vec![
+                json::Json::String(String::from("application_credential"))])),
+            ("application_credential", json_object(credential))
+        ]);
+        json_object(vec![
+            ("auth", json_object(vec![("identity", identity)]))
+        ])
+    }
+
+    /*
+     * Authenticate using supplied parameters
+     */
+    fn authenticate(&self, session_token: &mut SessionToken) -> Result<(), AuthError> {
+        debug!("Starting v3 application credential authentication");
+        let payload = self.auth_payload();
+        authenticate_v3(&self.client, &self.auth_url, &payload,
+                        &self.filter, session_token)
+    }
+}
+
+impl CatalogAuth for KeystoneAuthV3AppCredential {
+    fn endpoint_filter_mut(&mut self) -> &mut EndpointFilter {
+        &mut self.filter
+    }
+}
+
+impl Auth for KeystoneAuthV3AppCredential {
+    fn build_request(&self, m: Method, path: String, headers: Headers)
+            -> Result<RequestBuilder, AuthError> {
+        self.build_request_with_token(m, path, headers).map(|(r, _)| r)
+    }
+
+    fn build_request_with_token(&self, m: Method, path: String, headers: Headers)
+            -> Result<(RequestBuilder, Option<String>), AuthError> {
+        // Make sure we have a valid auth token
+        let session_token = try!(get_token(&self.token, |t| self.authenticate(t)));
+        build_token_request(&self.client, session_token, m, path, headers)
+    }
+
+    fn invalidate_token(&self, rejected: &str) -> bool {
+        invalidate_token(&self.token, rejected)
+    }
+
+    fn session_info(&self) -> Result<SessionInfo, AuthError> {
+        let session_token = try!(get_token(&self.token, |t| self.authenticate(t)));
+        snapshot_session(session_token)
+    }
+}
+
+/*
+ *  Swift TempAuth (v1.0)
+ */
+
+header! { (XAuthUser, "X-Auth-User") => [String] }
+header! { (XAuthKey, "X-Auth-Key") => [String] }
+header! { (XStorageUrl, "X-Storage-Url") => [String] }
+header! { (XAuthTokenExpires, "X-Auth-Token-Expires") => [i64] }
+
+pub struct TempAuth {
+    user: String,
+    key: String,
+    auth_url: String,
+    client: Client,
+    token: Mutex<SessionToken>,
+}
+
+unsafe impl Send for TempAuth {}
+unsafe impl Sync for TempAuth {}
+
+impl TempAuth {
+    pub fn new (user: String, key: String, auth_url: String) -> TempAuth {
+        let client = Client::new().unwrap();
+        let token = SessionToken::new();
+        TempAuth {
+            user: user,
+            key: key,
+            auth_url: auth_url,
+            client: client,
+            token: Mutex::new(token)
+        }
+    }
+
+    /*
+     * Authenticate using supplied parameters. TempAuth returns everything
+     * we need in the response headers.
+     */
+    fn authenticate(&self, session_token: &mut SessionToken) -> Result<(), AuthError> {
+        debug!("Starting TempAuth authentication");
+        let mut headers = Headers::new();
+        headers.set(XAuthUser(self.user.clone()));
+        headers.set(XAuthKey(self.key.clone()));
+        let res = match self.client.get(&self.auth_url[..]).headers(headers).send() {
+            Ok(r) => r,
+            Err(e) => return Err(AuthError::Http(e))
+        };
+        if !res.status().is_success() {
+            error!("TempAuth authentication failed: {}", res.status());
+            let err_msg = format!("TempAuth authentication failed: {}", res.status());
+            return Err(AuthError::Fail(err_msg))
+        }
+
+        let token = match res.headers().get::<XAuthToken>() {
+            Some(t) => t.to_string(),
             None => {
-                error!("No current access token found");
-                let err_msg = String::from("No current access token found");
+                error!("No X-Auth-Token found in response");
+                let err_msg = String::from("No X-Auth-Token found in response");
                 return Err(AuthError::Fail(err_msg))
             }
         };
-        let storage_base_url: &String = match keystone_token.storage_url {
-            Some(ref u) => u,
+        let storage_url = match res.headers().get::<XStorageUrl>() {
+            Some(u) => u.to_string(),
             None => {
-                error!("No storage base URL found");
-                let err_msg = String::from("No storage base URL found");
+                error!("No X-Storage-Url found in response");
+                let err_msg = String::from("No X-Storage-Url found in response");
                 return Err(AuthError::Fail(err_msg))
             }
         };
-        let mut url = String::from("");
-        url.push_str(storage_base_url);
-        url.push_str(&path);
-        debug!("Request base URL: {}", url);
-        match url.into_url() {
-            Ok(_u) => {
-                headers.set(XAuthToken(token));
-                return Ok(self.client.request(m, _u).headers(headers))
-            }
-            _ => {
-                error!("Failed to parse request base URL: {}", url);
-                let err_msg = String::from("Failed to parse base request URL");
-                return Err(AuthError::Fail(err_msg))
-            }
+        // The expiry is given as a number of seconds from now. Without one,
+        // the token is used until it's rejected.
+        let expires = match res.headers().get::<XAuthTokenExpires>() {
+            Some(&XAuthTokenExpires(secs)) => Some(UTC::now() + Duration::seconds(secs)),
+            None => None
+        };
+
+        session_token.token = Some(token);
+        session_token.storage_url = Some(storage_url);
+        session_token.expires = expires;
+        Ok(())
+    }
+}
+
+impl Auth for TempAuth {
+    fn build_request(&self, m: Method, path: String, headers: Headers)
+            -> Result<RequestBuilder, AuthError> {
+        self.build_request_with_token(m, path, headers).map(|(r, _)| r)
+    }
+
+    fn build_request_with_token(&self, m: Method, path: String, headers: Headers)
+            -> Result<(RequestBuilder, Option<String>), AuthError> {
+        // Make sure we have a valid auth token
+        let session_token = try!(get_token(&self.token, |t| self.authenticate(t)));
+        build_token_request(&self.client, session_token, m, path, headers)
+    }
+
+    fn invalidate_token(&self, rejected: &str) -> bool {
+        invalidate_token(&self.token, rejected)
+    }
+
+    fn session_info(&self) -> Result<SessionInfo, AuthError> {
+        let session_token = try!(get_token(&self.token, |t| self.authenticate(t)));
+        snapshot_session(session_token)
+    }
+}
+
+/*
+ *  Pre-authenticated token and storage URL
+ */
+
+pub struct StaticTokenAuth {
+    token: String,
+    storage_url: String,
+    client: Client,
+}
+
+unsafe impl Send for StaticTokenAuth {}
+unsafe impl Sync for StaticTokenAuth {}
+
+impl StaticTokenAuth {
+    pub fn new (token: String, storage_url: String) -> StaticTokenAuth {
+        let client = Client::new().unwrap();
+        StaticTokenAuth {
+            token: token,
+            storage_url: storage_url,
+            client: client
         }
     }
 }
+
+impl Auth for StaticTokenAuth {
+    fn build_request(&self, m: Method, path: String, headers: Headers)
+            -> Result<RequestBuilder, AuthError> {
+        // There's nothing to refresh, so a rejected token is only
+        // reported when the request is made
+        token_request(&self.client, &self.storage_url, self.token.clone(),
+                      m, path, headers)
+    }
+
+    fn session_info(&self) -> Result<SessionInfo, AuthError> {
+        Ok(SessionInfo::new(self.storage_url.clone()))
+    }
+}
+
+/*
+ * POST a v3 token request and store the resulting token, expiry time
+ * and object-store endpoint in the session token
+ */
+fn authenticate_v3(client: &Client, auth_url: &String, payload: &json::Json,
+                   filter: &EndpointFilter, session_token: &mut SessionToken)
+        -> Result<(), AuthError> {
+    let _au = &format!("{}/{}", auth_url.trim_right_matches('/'), "auth/tokens")[..];
+    let mut res = try!(post_json_response(client, _au, payload));
+    if !res.status().is_success() {
+        error!("Keystone v3 authentication failed: {}", res.status());
+        let err_msg = format!("Keystone v3 authentication failed: {}", res.status());
+        return Err(AuthError::Fail(err_msg))
+    }
+
+    // The v3 API returns the token in a header, not in the body
+    let token_id: Option<String> = match res.headers().get::<XSubjectToken>() {
+        Some(t) => Some(t.to_string()),
+        None => {
+            error!("No X-Subject-Token found in response");
+            let err_msg = String::from("No X-Subject-Token found in response");
+            return Err(AuthError::Fail(err_msg))
+        }
+    };
+
+    let response = try!(read_body(&mut res));
+    let response_object: json::Json = match json::Json::from_str(&response) {
+        Ok(j) => j,
+        Err(e) => return Err(AuthError::JsonDecode(e))
+    };
+
+    // Get the expiry time
+    let token: &json::Json = try!(find_err(&response_object, "token"));
+    let expires: &json::Json = try!(find_err(token, "expires_at"));
+
+    // Get the service catalogue and find the object store
+    let catalogue: &json::Json = try!(find_err(token, "catalog"));
+    let catalog = try!(parse_catalogue(catalogue, parse_endpoint_v3));
+    let endpoint = try!(filter.select(&catalog));
+
+    session_token.set_catalog(catalog, endpoint, token.find("project"));
+    session_token.token = token_id;
+    session_token.expires = Some(try!(parse_expiry(expires)));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, UTC};
+    use rustc_serialize::json::Json;
+    use std::sync::Mutex;
+
+    use auth::sessions::{
+        EndpointFilter, Interface, ServiceCatalog, SessionToken, get_token,
+        invalidate_token, parse_catalogue, parse_endpoint_v2, parse_endpoint_v3
+    };
+
+    const CATALOG_V3: &'static str = r#"[
+        {"type": "identity", "name": "keystone", "endpoints": [
+            {"interface": "public", "region_id": "RegionOne",
+             "url": "https://keystone.example.com/v3"}
+        ]},
+        {"type": "object-store", "name": "swift", "endpoints": [
+            {"interface": "public", "region_id": "RegionOne",
+             "url": "https://one.example.com/v1/AUTH_p"},
+            {"interface": "internal", "region_id": "RegionOne",
+             "url": "http://one.internal/v1/AUTH_p"},
+            {"interface": "public", "region": "RegionTwo",
+             "url": "https://two.example.com/v1/AUTH_p"},
+            {"interface": "unknown", "region_id": "RegionOne",
+             "url": "https://unknown.example.com/v1/AUTH_p"}
+        ]},
+        {"type": "object-store", "name": "swift-archive", "endpoints": [
+            {"interface": "public", "region_id": "RegionOne",
+             "url": "https://archive.example.com/v1/AUTH_p"}
+        ]}
+    ]"#;
+
+    fn catalog_v3() -> ServiceCatalog {
+        parse_catalogue(&Json::from_str(CATALOG_V3).unwrap(), parse_endpoint_v3).unwrap()
+    }
+
+    fn selected_url(filter: &EndpointFilter) -> Option<String> {
+        filter.select(&catalog_v3()).ok().map(|e| e.url)
+    }
+
+    #[test]
+    fn catalogue_v3() {
+        let catalog = catalog_v3();
+        assert_eq!(catalog.services().len(), 3);
+        let swift = catalog.services_of_type("object-store")[0];
+        assert_eq!(swift.name(), Some("swift"));
+        // The endpoint with an unknown interface is skipped
+        let endpoints: Vec<(Option<&str>, Interface, &str)> = swift.endpoints().iter()
+            .map(|e| (e.region(), e.interface(), e.url())).collect();
+        assert_eq!(endpoints, vec![
+            (Some("RegionOne"), Interface::Public, "https://one.example.com/v1/AUTH_p"),
+            (Some("RegionOne"), Interface::Internal, "http://one.internal/v1/AUTH_p"),
+            (Some("RegionTwo"), Interface::Public, "https://two.example.com/v1/AUTH_p")
+        ]);
+    }
+
+    #[test]
+    fn catalogue_v2() {
+        let body = r#"[
+            {"type": "object-store", "name": "swift", "endpoints": [
+                {"region": "RegionOne",
+                 "publicURL": "https://one.example.com/v1/AUTH_p",
+                 "internalURL": "http://one.internal/v1/AUTH_p"}
+            ]}
+        ]"#;
+        let catalog = parse_catalogue(&Json::from_str(body).unwrap(),
+                                      parse_endpoint_v2).unwrap();
+        let endpoints: Vec<(Option<&str>, Interface, &str)> =
+            catalog.services()[0].endpoints().iter()
+            .map(|e| (e.region(), e.interface(), e.url())).collect();
+        assert_eq!(endpoints, vec![
+            (Some("RegionOne"), Interface::Public, "https://one.example.com/v1/AUTH_p"),
+            (Some("RegionOne"), Interface::Internal, "http://one.internal/v1/AUTH_p")
+        ]);
+    }
+
+    #[test]
+    fn catalogue_without_type() {
+        let body = r#"[{"name": "swift", "endpoints": []}]"#;
+        assert!(parse_catalogue(&Json::from_str(body).unwrap(), parse_endpoint_v3).is_err());
+    }
+
+    #[test]
+    fn select_last_matching_service() {
+        let filter = EndpointFilter::new(None, Interface::Public);
+        assert_eq!(selected_url(&filter),
+                   Some(String::from("https://archive.example.com/v1/AUTH_p")));
+    }
+
+    #[test]
+    fn select_by_service_name() {
+        let mut filter = EndpointFilter::new(None, Interface::Public);
+        filter.service_name = Some(String::from("swift"));
+        assert_eq!(selected_url(&filter),
+                   Some(String::from("https://one.example.com/v1/AUTH_p")));
+        filter.service_name = Some(String::from("missing"));
+        assert_eq!(selected_url(&filter), None);
+    }
+
+    #[test]
+    fn select_by_region_and_interface() {
+        // The last service has no RegionTwo endpoint, so the one before it is used
+        let filter = EndpointFilter::new(Some(String::from("RegionTwo")), Interface::Public);
+        assert_eq!(selected_url(&filter),
+                   Some(String::from("https://two.example.com/v1/AUTH_p")));
+        let filter = EndpointFilter::new(
+            Some(String::from("RegionOne")), Interface::Internal);
+        assert_eq!(selected_url(&filter),
+                   Some(String::from("http://one.internal/v1/AUTH_p")));
+        let filter = EndpointFilter::new(
+            Some(String::from("RegionTwo")), Interface::Internal);
+        assert_eq!(selected_url(&filter), None);
+        let filter = EndpointFilter::new(None, Interface::Admin);
+        assert_eq!(selected_url(&filter), None);
+    }
+
+    #[test]
+    fn select_by_service_type() {
+        let mut filter = EndpointFilter::new(None, Interface::Public);
+        filter.service_type = String::from("identity");
+        assert_eq!(selected_url(&filter),
+                   Some(String::from("https://keystone.example.com/v3")));
+        filter.service_type = String::from("volume");
+        assert_eq!(selected_url(&filter), None);
+    }
+
+    #[test]
+    fn select_storage_url_override() {
+        let mut filter = EndpointFilter::new(None, Interface::Public);
+        filter.storage_url = Some(String::from("https://override.example.com/v1/AUTH_p"));
+        assert_eq!(selected_url(&filter),
+                   Some(String::from("https://override.example.com/v1/AUTH_p")));
+    }
+
+    // A token issued the given time ago, that expires the given time from now
+    fn token(issued_ago: Duration, expires_in: Option<Duration>) -> SessionToken {
+        let now = UTC::now();
+        let mut token = SessionToken::new();
+        token.token = Some(String::from("token"));
+        token.issued = Some(now - issued_ago);
+        token.expires = expires_in.map(|e| now + e);
+        token
+    }
+
+    #[test]
+    fn long_lived_token() {
+        // Refreshed an hour before it expires
+        assert!(token(Duration::zero(), Some(Duration::hours(24))).is_valid());
+        assert!(token(Duration::hours(22), Some(Duration::minutes(61))).is_valid());
+        assert!(!token(Duration::hours(23), Some(Duration::minutes(59))).is_valid());
+    }
+
+    #[test]
+    fn short_lived_token() {
+        // A 20 minute token is refreshed 5 minutes before it expires
+        assert!(token(Duration::zero(), Some(Duration::minutes(20))).is_valid());
+        assert!(token(Duration::minutes(14), Some(Duration::minutes(6))).is_valid());
+        assert!(!token(Duration::minutes(16), Some(Duration::minutes(4))).is_valid());
+        assert!(!token(Duration::minutes(21), Some(Duration::minutes(-1))).is_valid());
+    }
+
+    #[test]
+    fn token_without_expiry() {
+        assert!(token(Duration::days(30), None).is_valid());
+        let mut missing = token(Duration::zero(), None);
+        missing.token = None;
+        assert!(!missing.is_valid());
+    }
+
+    fn authenticate_as(lock: &Mutex<SessionToken>, token: &str) -> String {
+        get_token(lock, |t| {
+            t.token = Some(String::from(token));
+            Ok(())
+        }).unwrap().token.unwrap()
+    }
+
+    #[test]
+    fn invalidate_rejected_token() {
+        let lock = Mutex::new(SessionToken::new());
+        assert_eq!(authenticate_as(&lock, "first"), "first");
+        // Still valid, so there's no need to authenticate again
+        assert_eq!(authenticate_as(&lock, "second"), "first");
+        assert!(invalidate_token(&lock, "first"));
+        assert_eq!(authenticate_as(&lock, "second"), "second");
+    }
+
+    #[test]
+    fn keep_replaced_token() {
+        let lock = Mutex::new(SessionToken::new());
+        authenticate_as(&lock, "first");
+        invalidate_token(&lock, "first");
+        authenticate_as(&lock, "second");
+        // A late rejection of the old token doesn't throw away the new one
+        assert!(invalidate_token(&lock, "first"));
+        assert_eq!(authenticate_as(&lock, "third"), "second");
+        // But the new one is thrown away when it's rejected itself
+        assert!(invalidate_token(&lock, "second"));
+        assert_eq!(authenticate_as(&lock, "third"), "third");
+    }
+}
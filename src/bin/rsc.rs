@@ -11,7 +11,10 @@ use std::process::exit;
 use std::thread;
 use std::sync::Arc;
 
-use rust_swiftclient::auth::sessions::KeystoneAuthV2;
+use rust_swiftclient::auth::sessions::{
+    Auth, CatalogAuth, Interface, KeystoneAuthV2, KeystoneAuthV3,
+    KeystoneAuthV3AppCredential, StaticTokenAuth, TempAuth
+};
 use rust_swiftclient::client::request::{
     RunSwiftRequest, SwiftConnection
 };
@@ -25,6 +28,16 @@ Options:
     -A, --auth-url=<url>       URL of the auth system (must be specified or set in env[$OS_AUTH_URL])
     -P, --password=<password>  password (must be specified or set in env[$OS_PASSWORD])
     -R, --region=<region>      region (optional, can be set in env[$OS_REGION_NAME])
+    --os-interface=<i>         endpoint interface, public, internal or admin (default public, can be set in env[$OS_INTERFACE])
+    -V, --auth-version=<v>     auth API version, 1 (TempAuth), 2 or 3 (default 2, can be set in env[$OS_IDENTITY_API_VERSION])
+    --user-domain-name=<d>     v3 user domain (default Default, can be set in env[$OS_USER_DOMAIN_NAME])
+    --project-domain-name=<d>  v3 project domain (default Default, can be set in env[$OS_PROJECT_DOMAIN_NAME])
+    --app-cred-id=<id>         v3 application credential id (optional, can be set in env[$OS_APPLICATION_CREDENTIAL_ID])
+    --app-cred-secret=<s>      v3 application credential secret (can be set in env[$OS_APPLICATION_CREDENTIAL_SECRET])
+    --os-service-type=<t>      object store service type (default object-store, can be set in env[$OS_SERVICE_TYPE])
+    --os-service-name=<n>      object store service name (optional, can be set in env[$OS_SERVICE_NAME])
+    --os-storage-url=<url>     storage URL, overriding the catalogue or used with --os-auth-token (can be set in env[$OS_STORAGE_URL])
+    --os-auth-token=<token>    pre-authenticated token, used with --os-storage-url (can be set in env[$OS_AUTH_TOKEN])
     -h, --help                 display this help and exit
     -v, --version              output version information and exit
 ";
@@ -36,7 +49,33 @@ struct Args {
     flag_tenant: Option<String>,
     flag_auth_url: Option<String>,
     flag_password: Option<String>,
-    flag_region: Option<String>
+    flag_region: Option<String>,
+    flag_os_interface: Option<String>,
+    flag_auth_version: Option<String>,
+    flag_user_domain_name: Option<String>,
+    flag_project_domain_name: Option<String>,
+    flag_app_cred_id: Option<String>,
+    flag_app_cred_secret: Option<String>,
+    flag_os_service_type: Option<String>,
+    flag_os_service_name: Option<String>,
+    flag_os_storage_url: Option<String>,
+    flag_os_auth_token: Option<String>
+}
+
+// Apply any catalogue lookup options to a Keystone session
+fn catalog_options<A: CatalogAuth>(mut auth: A, service_type: Option<String>,
+                                   service_name: Option<String>,
+                                   storage_url: Option<String>) -> A {
+    if let Some(t) = service_type {
+        auth = auth.with_service_type(t);
+    }
+    if let Some(n) = service_name {
+        auth = auth.with_service_name(n);
+    }
+    if let Some(u) = storage_url {
+        auth = auth.with_storage_url(u);
+    }
+    auth
 }
 
 // I think this stuff needs to be moved to a separate options handler
@@ -68,16 +107,100 @@ fn main() {
                             .and_then(|dopt| dopt.decode())
                             .unwrap_or_else(|e| e.exit());
 
-    let user = get_arg(args.flag_user, String::from("OS_USERNAME"));
-    let pwd = get_arg(args.flag_password, String::from("OS_PASSWORD"));
-    let tenant = get_arg(args.flag_tenant, String::from("OS_PROJECT_NAME"));
+    // A token and storage URL that we've been given don't need any auth
+    let storage_url = get_optional_arg(
+        args.flag_os_storage_url, String::from("OS_STORAGE_URL"));
+    let auth_token = get_optional_arg(
+        args.flag_os_auth_token, String::from("OS_AUTH_TOKEN"));
+    // Without a token, a storage URL just overrides the catalogue
+    let storage_url = match (storage_url, auth_token) {
+        (Some(storage_url), Some(auth_token)) => {
+            let static_auth = StaticTokenAuth::new(auth_token, storage_url);
+            run(Arc::new(SwiftConnection::new(static_auth)));
+            return
+        },
+        (storage_url, None) => storage_url,
+        (None, Some(_)) => {
+            println!("{}", USAGE);
+            exit(1);
+        }
+    };
+    let service_type = get_optional_arg(
+        args.flag_os_service_type, String::from("OS_SERVICE_TYPE"));
+    let service_name = get_optional_arg(
+        args.flag_os_service_name, String::from("OS_SERVICE_NAME"));
+
     let url = get_arg(args.flag_auth_url, String::from("OS_AUTH_URL"));
     let region = get_optional_arg(args.flag_region, String::from("OS_REGION_NAME"));
+    let interface = match get_optional_arg(
+        args.flag_os_interface, String::from("OS_INTERFACE")
+    ) {
+        Some(i) => match i.parse::<Interface>() {
+            Ok(i) => i,
+            Err(e) => {
+                println!("{}", e);
+                exit(1);
+            }
+        },
+        None => Interface::Public
+    };
+
+    // An application credential takes precedence over user credentials,
+    // and is always authenticated against the v3 API
+    let app_cred_id = get_optional_arg(
+        args.flag_app_cred_id, String::from("OS_APPLICATION_CREDENTIAL_ID"));
+    if let Some(id) = app_cred_id {
+        let secret = get_arg(
+            args.flag_app_cred_secret, String::from("OS_APPLICATION_CREDENTIAL_SECRET"));
+        let ksauth = catalog_options(
+            KeystoneAuthV3AppCredential::new_with_id(
+                id, secret, url, region, interface),
+            service_type, service_name, storage_url);
+        run(Arc::new(SwiftConnection::new(ksauth)));
+        return
+    }
+
+    let user = get_arg(args.flag_user, String::from("OS_USERNAME"));
+    let pwd = get_arg(args.flag_password, String::from("OS_PASSWORD"));
+
+    let version = get_optional_arg(
+        args.flag_auth_version, String::from("OS_IDENTITY_API_VERSION"));
 
-    let ksauth = KeystoneAuthV2::new(user, pwd, tenant, url, region);
-    let swift: Arc<SwiftConnection<KeystoneAuthV2>> =
-        Arc::new(SwiftConnection::new(ksauth));
+    match version.as_ref().map(|v| v.trim_left_matches('v')) {
+        Some("1") | Some("1.0") => {
+            let tempauth = TempAuth::new(user, pwd, url);
+            run(Arc::new(SwiftConnection::new(tempauth)));
+        },
+        Some("3") => {
+            let tenant = get_arg(args.flag_tenant, String::from("OS_PROJECT_NAME"));
+            let user_domain = get_optional_arg(
+                args.flag_user_domain_name, String::from("OS_USER_DOMAIN_NAME")
+            ).unwrap_or(String::from("Default"));
+            let project_domain = get_optional_arg(
+                args.flag_project_domain_name, String::from("OS_PROJECT_DOMAIN_NAME")
+            ).unwrap_or(String::from("Default"));
+            let ksauth = catalog_options(
+                KeystoneAuthV3::new(
+                    user, pwd, user_domain, tenant, project_domain, url,
+                    region, interface),
+                service_type, service_name, storage_url);
+            run(Arc::new(SwiftConnection::new(ksauth)));
+        },
+        Some("2") | Some("2.0") | None => {
+            let tenant = get_arg(args.flag_tenant, String::from("OS_PROJECT_NAME"));
+            let ksauth = catalog_options(
+                KeystoneAuthV2::new(user, pwd, tenant, url, region, interface),
+                service_type, service_name, storage_url);
+            run(Arc::new(SwiftConnection::new(ksauth)));
+        },
+        Some(v) => {
+            println!("Unsupported identity API version: {}", v);
+            exit(1);
+        }
+    };
+}
 
+fn run<A: Auth + 'static>(swift: Arc<SwiftConnection<A>>) {
     // For now, just do **something** in a separate thread and produce output
     let _ = {
         let sw = swift.clone();
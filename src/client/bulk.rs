@@ -0,0 +1,102 @@
+use reqwest::{Response, StatusCode};
+use rustc_serialize::json::Json;
+use std::collections::BTreeSet;
+use url::percent_encoding::percent_decode;
+
+use client::errors::SwiftError;
+use client::response::read_body;
+
+// The middleware's default limit
+pub const DEFAULT_MAX_DELETES_PER_REQUEST: u64 = 10000;
+
+/*
+ * Bulk delete reports, from the bulk middleware
+ */
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BulkDeleteFailure {
+    // As "container/object", or "container"
+    pub path: String,
+    // e.g. "409 Conflict"
+    pub status: String
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BulkDeleteResult {
+    // Every path that wasn't reported as failed, including those that
+    // had already been deleted
+    pub deleted: Vec<String>,
+    pub failed: Vec<BulkDeleteFailure>,
+    // The counts reported by Swift
+    pub number_deleted: u64,
+    pub number_not_found: u64
+}
+
+/*
+ * The report looks like
+ *   {"Number Deleted": 1, "Number Not Found": 0,
+ *    "Response Status": "400 Bad Request", "Response Body": "",
+ *    "Errors": [["/c/o", "409 Conflict"]]}
+ * and only says which paths failed, so the rest of the batch succeeded
+ */
+pub fn parse_bulk_delete(mut resp: Response, paths: Vec<String>,
+                         result: &mut BulkDeleteResult)
+    -> Result<(), SwiftError>
+{
+    let body = try!(read_body(&mut resp));
+    let report = match Json::from_str(&body) {
+        Ok(j) => j,
+        Err(e) => return Err(SwiftError::Parse(format!("{}", e)))
+    };
+    let mut failed_paths = BTreeSet::new();
+    if let Some(errors) = report.find("Errors").and_then(|e| e.as_array()) {
+        for error in errors {
+            let pair = error.as_array();
+            let path = pair.and_then(|p| p.get(0)).and_then(|p| p.as_string());
+            let status = pair.and_then(|p| p.get(1)).and_then(|s| s.as_string());
+            match (path, status) {
+                (Some(path), Some(status)) => {
+                    let path = normalise_path(path);
+                    failed_paths.insert(path.clone());
+                    result.failed.push(BulkDeleteFailure {
+                        path: path,
+                        status: String::from(status)
+                    });
+                },
+                _ => return Err(SwiftError::Parse(
+                    format!("Invalid bulk delete error: {}", error)))
+            };
+        }
+    }
+
+    /*
+     * The status is 400 whenever anything failed, but a response body
+     * means the batch was abandoned part way (e.g. after too many
+     * failures), so there's no telling which of the rest were deleted
+     */
+    let status = report.find("Response Status").and_then(|s| s.as_string()).unwrap_or("");
+    let body = report.find("Response Body").and_then(|b| b.as_string()).unwrap_or("");
+    if !status.starts_with('2') && (failed_paths.is_empty() || !body.is_empty()) {
+        let code = status.split(' ').next().and_then(|c| c.parse::<u16>().ok());
+        let err_msg = format!("Bulk delete failed ({}): {}", status, body);
+        return match code {
+            Some(c) => Err(SwiftError::Status(StatusCode::from_u16(c), err_msg)),
+            None => Err(SwiftError::Parse(err_msg))
+        }
+    }
+
+    result.number_deleted += report.find("Number Deleted").and_then(|n| n.as_u64()).unwrap_or(0);
+    result.number_not_found += report.find("Number Not Found").and_then(
+        |n| n.as_u64()).unwrap_or(0);
+    for path in paths {
+        if !failed_paths.contains(&normalise_path(&path)) {
+            result.deleted.push(path);
+        }
+    }
+    Ok(())
+}
+
+// Swift reports the paths quoted, and with a leading slash
+fn normalise_path(path: &str) -> String {
+    percent_decode(path.as_bytes()).decode_utf8_lossy().trim_matches('/').to_string()
+}
@@ -0,0 +1,88 @@
+use crypto::digest::Digest;
+use crypto::md5::Md5;
+use std::io::{self, Read};
+use std::sync::{Arc, Mutex};
+
+use client::errors::SwiftError;
+
+/*
+ * MD5 checksums, which Swift uses as the etag of ordinary objects
+ */
+
+pub fn md5_hex(data: &[u8]) -> String {
+    let mut md5 = Md5::new();
+    md5.input(data);
+    md5.result_str()
+}
+
+// Swift may quote etags, e.g. for SLO manifests
+pub fn unquote_etag(etag: &str) -> &str {
+    etag.trim().trim_matches('"')
+}
+
+/*
+ * Large objects have quoted etags, which are the MD5 of their segments'
+ * etags rather than of their data
+ */
+pub fn is_manifest_etag(etag: &str) -> bool {
+    let etag = etag.trim();
+    etag.len() >= 2 && etag.starts_with('"') && etag.ends_with('"')
+}
+
+pub fn check_md5(name: &str, expected: &str, actual: &str) -> Result<(), SwiftError> {
+    if unquote_etag(expected) != actual {
+        let err_msg = format!("{} has MD5 {}, expected {}",
+                              name, actual, unquote_etag(expected));
+        return Err(SwiftError::ChecksumMismatch(err_msg))
+    }
+    Ok(())
+}
+
+/*
+ * The digest of everything read so far by an Md5Reader. It can still
+ * be read once the reader has been handed over, e.g. as a request body.
+ */
+#[derive(Clone)]
+pub struct Md5Digest {
+    md5: Arc<Mutex<Md5>>
+}
+
+impl Md5Digest {
+    pub fn hex(&self) -> String {
+        match self.md5.lock() {
+            Ok(md5) => md5.clone().result_str(),
+            Err(poisoned) => poisoned.into_inner().clone().result_str()
+        }
+    }
+}
+
+pub struct Md5Reader<R> {
+    inner: R,
+    digest: Md5Digest
+}
+
+impl<R: Read> Md5Reader<R> {
+    pub fn new(inner: R) -> Md5Reader<R> {
+        Md5Reader {
+            inner: inner,
+            digest: Md5Digest {
+                md5: Arc::new(Mutex::new(Md5::new()))
+            }
+        }
+    }
+
+    pub fn digest(&self) -> Md5Digest {
+        self.digest.clone()
+    }
+}
+
+impl<R: Read> Read for Md5Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = try!(self.inner.read(buf));
+        match self.digest.md5.lock() {
+            Ok(mut md5) => md5.input(&buf[..n]),
+            Err(poisoned) => poisoned.into_inner().input(&buf[..n])
+        };
+        Ok(n)
+    }
+}
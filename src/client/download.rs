@@ -0,0 +1,416 @@
+use hyper::header::ByteRangeSpec;
+use rustc_serialize::json::Json;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use auth::sessions::Auth;
+use client::errors::SwiftError;
+use client::checksum::{Md5Reader, check_md5, md5_hex, unquote_etag};
+use client::listing::ObjectEntry;
+use client::object::{ObjectManifest, ObjectMetadata};
+use client::parallel::map_ordered;
+use client::request::{
+    ContainerIterator, GetObject, GetObjectResult, HeadObject, RunSwiftRequest,
+    RunTypedSwiftRequest
+};
+use client::response::{check_status, read_body};
+use client::retry::with_retries;
+
+/*
+ * Parallel ranged download of an object into a file
+ */
+
+pub const DEFAULT_RANGE_SIZE: u64 = 100 * 1024 * 1024;
+pub const DEFAULT_RANGE_RETRIES: u32 = 3;
+pub const DEFAULT_DOWNLOAD_CONCURRENCY: usize = 4;
+
+pub struct ObjectDownload<A> {
+    container: String,
+    object: String,
+    path: PathBuf,
+    range_size: u64,
+    concurrency: usize,
+    retries: u32,
+    verify: bool,
+    auth: Arc<A>
+}
+
+impl<AS: Sized+Auth> ObjectDownload<AS> {
+    pub fn new(
+        auth: Arc<AS>, container: String, object: String, path: PathBuf
+    ) -> ObjectDownload<AS> {
+        ObjectDownload {
+            container: container,
+            object: object,
+            path: path,
+            range_size: DEFAULT_RANGE_SIZE,
+            concurrency: DEFAULT_DOWNLOAD_CONCURRENCY,
+            retries: DEFAULT_RANGE_RETRIES,
+            verify: true,
+            auth: auth
+        }
+    }
+
+    pub fn range_size(mut self, range_size: u64) -> ObjectDownload<AS> {
+        self.range_size = range_size;
+        self
+    }
+
+    pub fn concurrency(mut self, concurrency: usize) -> ObjectDownload<AS> {
+        self.concurrency = concurrency;
+        self
+    }
+
+    // How many times to retry each range before giving up
+    pub fn retries(mut self, retries: u32) -> ObjectDownload<AS> {
+        self.retries = retries;
+        self
+    }
+
+    /*
+     * Check the downloaded file against the object's etag, which means
+     * reading it back once the download is done
+     */
+    pub fn verify(mut self, verify: bool) -> ObjectDownload<AS> {
+        self.verify = verify;
+        self
+    }
+}
+
+impl<AS: Sized+Auth+Send+Sync+'static> RunTypedSwiftRequest for ObjectDownload<AS> {
+    type Output = ObjectMetadata;
+
+    fn run_typed_request(self)
+            -> Result<ObjectMetadata, SwiftError> {
+        let meta = try!(HeadObject::new(
+            self.auth.clone(), self.container.clone(), self.object.clone()
+        ).run_typed_request());
+        let size = meta.content_length;
+
+        // Each worker opens the file itself, to write at its own offsets
+        let file = try!(File::create(&self.path).map_err(SwiftError::Io));
+        try!(file.set_len(size).map_err(SwiftError::Io));
+        drop(file);
+
+        /*
+         * Make sure every range comes from the same version of the
+         * object. DLOs are assembled from a listing on each GET, so
+         * there's no etag to match.
+         */
+        let if_match = match meta.manifest {
+            Some(ObjectManifest::Dynamic { .. }) => None,
+            _ => meta.etag.clone()
+        };
+        let target = RangeTarget {
+            container: self.container.clone(),
+            object: self.object.clone(),
+            if_match: if_match,
+            path: self.path.clone(),
+            auth: self.auth.clone()
+        };
+        let ranges = split_ranges(size, self.range_size).into_iter().map(Ok);
+        let retries = self.retries;
+        let lengths = try!(map_ordered(ranges, self.concurrency, move |_, (start, end)| {
+            target.fetch(start, end, retries)
+        }));
+
+        // The file already has the full length, so count what was written
+        let written = lengths.iter().fold(0, |t, l| t + *l);
+        if written != size {
+            let err_msg = format!(
+                "Downloaded {} bytes of {}/{}, expected {}",
+                written, self.container, self.object, size);
+            return Err(SwiftError::Parse(err_msg))
+        }
+        if self.verify {
+            try!(verify_download(
+                &self.auth, &self.container, &self.object, &meta,
+                &self.path, self.concurrency));
+        }
+        Ok(meta)
+    }
+}
+
+// Inclusive (start, end) pairs, as used in Range headers
+fn split_ranges(size: u64, range_size: u64) -> Vec<(u64, u64)> {
+    let range_size = if range_size == 0 { size } else { range_size };
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    while start < size {
+        let end = if size - start > range_size { start + range_size } else { size };
+        ranges.push((start, end - 1));
+        start = end;
+    }
+    ranges
+}
+
+// Where each range is fetched from and written to
+struct RangeTarget<A> {
+    container: String,
+    object: String,
+    if_match: Option<String>,
+    path: PathBuf,
+    auth: Arc<A>
+}
+
+impl<AS: Sized+Auth> RangeTarget<AS> {
+    /*
+     * Returns the number of bytes written. A failed If-Match (412) means
+     * the object has changed, so it isn't retried.
+     */
+    fn fetch(&self, start: u64, end: u64, retries: u32) -> Result<u64, SwiftError> {
+        let description = format!("Range {}-{} of {}/{}",
+                                  start, end, self.container, self.object);
+        with_retries(retries, &description, || self.write(start, end))
+    }
+
+    fn write(&self, start: u64, end: u64) -> Result<u64, SwiftError> {
+        let mut req = GetObject::new(
+            self.auth.clone(), self.container.clone(), self.object.clone()
+        ).range(ByteRangeSpec::FromTo(start, end));
+        if let Some(ref etag) = self.if_match {
+            req = req.if_match(etag.clone());
+        }
+        let mut resp = match try!(req.run_typed_request()) {
+            GetObjectResult::Partial(_, range, resp) => {
+                if range.start != start || range.end != end {
+                    let err_msg = format!(
+                        "Asked for range {}-{} of {}/{}, got {}-{}", start, end,
+                        self.container, self.object, range.start, range.end);
+                    return Err(SwiftError::Parse(err_msg))
+                }
+                resp
+            },
+            _ => {
+                let err_msg = format!("Range {}-{} not returned for {}/{}",
+                                      start, end, self.container, self.object);
+                return Err(SwiftError::Parse(err_msg))
+            }
+        };
+        let mut file = try!(
+            OpenOptions::new().write(true).open(&self.path).map_err(SwiftError::Io));
+        try!(file.seek(SeekFrom::Start(start)).map_err(SwiftError::Io));
+        let expected = end - start + 1;
+        let copied = try!(io::copy(&mut resp.by_ref().take(expected), &mut file)
+                          .map_err(SwiftError::Io));
+        // Treated as a transport error, so the range is fetched again
+        if copied != expected {
+            let err_msg = format!("Range {}-{} of {}/{} was truncated at {} bytes",
+                                  start, end, self.container, self.object, copied);
+            return Err(SwiftError::Io(io::Error::new(io::ErrorKind::UnexpectedEof, err_msg)))
+        }
+        Ok(copied)
+    }
+}
+
+/*
+ * Verification. The etag of an ordinary object is the MD5 of its data,
+ * while the etag of a large object is the MD5 of its segments' etags, so
+ * each segment is checked against the manifest instead.
+ */
+
+struct Piece {
+    offset: u64,
+    length: u64,
+    // None if the etag can't be checked against the data
+    hash: Option<String>
+}
+
+fn verify_download<AS: Sized+Auth+Send+Sync+'static>(
+    auth: &Arc<AS>, container: &str, object: &str, meta: &ObjectMetadata,
+    path: &PathBuf, concurrency: usize
+) -> Result<(), SwiftError> {
+    let etag = match meta.etag {
+        Some(ref e) => String::from(unquote_etag(e)),
+        None => return Ok(())
+    };
+    let pieces = match meta.manifest {
+        None => vec![Piece {
+            offset: 0,
+            length: meta.content_length,
+            hash: Some(etag)
+        }],
+        Some(ObjectManifest::Static) => try!(slo_pieces(auth, container, object, &etag)),
+        Some(ObjectManifest::Dynamic { container: ref c, ref prefix }) =>
+            try!(dlo_pieces(auth, c, prefix, &etag))
+    };
+    let total = pieces.iter().fold(0, |t, p| t + p.length);
+    if total != meta.content_length {
+        let err_msg = format!("Segments of {}/{} add up to {} bytes, expected {}",
+                              container, object, total, meta.content_length);
+        return Err(SwiftError::Parse(err_msg))
+    }
+
+    let pieces = pieces.into_iter().filter(|p| p.hash.is_some()).map(Ok);
+    let path = path.clone();
+    let name = format!("{}/{}", container, object);
+    try!(map_ordered(pieces, concurrency, move |_, piece| {
+        let hash = piece.hash.unwrap_or(String::new());
+        let actual = try!(md5_file_range(&path, piece.offset, piece.length)
+                          .map_err(SwiftError::Io));
+        check_md5(&format!("{} at {}", name, piece.offset), &hash, &actual)
+    }));
+    Ok(())
+}
+
+fn slo_pieces<AS: Sized+Auth>(
+    auth: &Arc<AS>, container: &str, object: &str, etag: &str
+) -> Result<Vec<Piece>, SwiftError> {
+    let mut resp = try!(check_status(try!(GetObject::new(
+        auth.clone(), String::from(container), String::from(object)
+    ).multipart_manifest_get(true).run_request())));
+    let body = try!(read_body(&mut resp));
+    let manifest = match Json::from_str(&body) {
+        Ok(j) => j,
+        Err(e) => return Err(SwiftError::Parse(format!("{}", e)))
+    };
+    let entries = match manifest.as_array() {
+        Some(a) => a,
+        None => return Err(SwiftError::Parse(String::from("Manifest is not a list")))
+    };
+
+    let mut pieces = Vec::new();
+    let mut etags = String::new();
+    let mut offset = 0;
+    for entry in entries {
+        let hash = entry.find("hash").and_then(|h| h.as_string());
+        let bytes = entry.find("bytes").and_then(|b| b.as_u64());
+        let (hash, bytes) = match (hash, bytes) {
+            (Some(h), Some(b)) => (String::from(h), b),
+            _ => return Err(SwiftError::Parse(format!("Invalid manifest entry: {}", entry)))
+        };
+        let range = entry.find("range").and_then(|r| r.as_string());
+        let length = match range {
+            Some(r) => {
+                etags.push_str(&format!("{}:{};", hash, r));
+                try!(segment_range_length(r, bytes))
+            },
+            None => {
+                etags.push_str(&hash);
+                bytes
+            }
+        };
+        // Nested manifests and partial segments can't be checked directly
+        let checkable = range.is_none() &&
+            entry.find("sub_slo").and_then(|s| s.as_boolean()) != Some(true);
+        pieces.push(Piece {
+            offset: offset,
+            length: length,
+            hash: if checkable { Some(hash) } else { None }
+        });
+        offset += length;
+    }
+    try!(check_manifest_etag(container, object, etag, &etags));
+    Ok(pieces)
+}
+
+fn dlo_pieces<AS: Sized+Auth>(
+    auth: &Arc<AS>, container: &str, prefix: &str, etag: &str
+) -> Result<Vec<Piece>, SwiftError> {
+    let segments = ContainerIterator::new(auth.clone(), String::from(container))
+        .prefix(prefix);
+    let mut pieces = Vec::new();
+    let mut etags = String::new();
+    let mut offset = 0;
+    for entry in segments {
+        let info = match try!(entry) {
+            ObjectEntry::Object(info) => info,
+            ObjectEntry::Subdir(_) => continue
+        };
+        let (hash, bytes) = match (info.hash, info.bytes) {
+            (Some(h), Some(b)) => (h, b),
+            _ => return Err(SwiftError::Parse(format!(
+                "Segment {} listed without a hash", info.name)))
+        };
+        etags.push_str(&hash);
+        pieces.push(Piece {
+            offset: offset,
+            length: bytes,
+            hash: Some(hash)
+        });
+        offset += bytes;
+    }
+    try!(check_manifest_etag(container, prefix, etag, &etags));
+    Ok(pieces)
+}
+
+fn check_manifest_etag(container: &str, object: &str, etag: &str, etags: &str)
+    -> Result<(), SwiftError>
+{
+    let name = format!("Manifest of {}/{}", container, object);
+    check_md5(&name, etag, &md5_hex(etags.as_bytes()))
+}
+
+// SLO segment ranges are "start-end", "start-" or "-suffix"
+fn segment_range_length(range: &str, bytes: u64) -> Result<u64, SwiftError> {
+    let mut parts = range.splitn(2, '-');
+    let start = parts.next().unwrap_or("").trim();
+    let end = parts.next().unwrap_or("").trim();
+    let parsed = match (start.parse::<u64>(), end.parse::<u64>()) {
+        (Ok(s), Ok(e)) if s <= e && e < bytes => Some(e - s + 1),
+        (Ok(s), Err(_)) if end.is_empty() && s < bytes => Some(bytes - s),
+        (Err(_), Ok(n)) if start.is_empty() && n <= bytes => Some(n),
+        _ => None
+    };
+    match parsed {
+        Some(length) => Ok(length),
+        None => Err(SwiftError::Parse(format!("Invalid segment range: {}", range)))
+    }
+}
+
+fn md5_file_range(path: &PathBuf, offset: u64, length: u64) -> io::Result<String> {
+    let mut file = try!(File::open(path));
+    try!(file.seek(SeekFrom::Start(offset)));
+    let mut reader = Md5Reader::new(file.take(length));
+    try!(io::copy(&mut reader, &mut io::sink()));
+    Ok(reader.digest().hex())
+}
+
+#[cfg(test)]
+mod tests {
+    use client::download::{segment_range_length, split_ranges};
+
+    #[test]
+    fn ranges_divide_evenly() {
+        assert_eq!(split_ranges(30, 10), vec![(0, 9), (10, 19), (20, 29)]);
+    }
+
+    #[test]
+    fn ranges_divide_unevenly() {
+        assert_eq!(split_ranges(25, 10), vec![(0, 9), (10, 19), (20, 24)]);
+        assert_eq!(split_ranges(11, 10), vec![(0, 9), (10, 10)]);
+    }
+
+    #[test]
+    fn smaller_than_one_range() {
+        assert_eq!(split_ranges(5, 10), vec![(0, 4)]);
+        assert_eq!(split_ranges(1, 10), vec![(0, 0)]);
+        assert_eq!(split_ranges(10, 10), vec![(0, 9)]);
+        // A range size of 0 means the whole object at once
+        assert_eq!(split_ranges(25, 0), vec![(0, 24)]);
+    }
+
+    #[test]
+    fn empty_object() {
+        assert_eq!(split_ranges(0, 10), Vec::<(u64, u64)>::new());
+        assert_eq!(split_ranges(0, 0), Vec::<(u64, u64)>::new());
+    }
+
+    #[test]
+    fn segment_ranges() {
+        assert_eq!(segment_range_length("0-9", 100).ok(), Some(10));
+        assert_eq!(segment_range_length("5-5", 100).ok(), Some(1));
+        assert_eq!(segment_range_length("90-", 100).ok(), Some(10));
+        assert_eq!(segment_range_length("-20", 100).ok(), Some(20));
+        assert_eq!(segment_range_length("-100", 100).ok(), Some(100));
+    }
+
+    #[test]
+    fn invalid_segment_ranges() {
+        for range in &["9-0", "0-100", "100-", "-101", "-", "", "a-b"] {
+            assert!(segment_range_length(range, 100).is_err(), "{}", range);
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use reqwest;
+use reqwest::StatusCode;
 use std::error;
 use std::fmt;
+use std::io;
 
 use auth::errors::AuthError;
 
@@ -11,7 +13,16 @@ use auth::errors::AuthError;
 #[derive(Debug)]
 pub enum SwiftError {
     Http(reqwest::Error),
-    Auth(AuthError)
+    Auth(AuthError),
+    Io(io::Error),
+    NotFound(String),
+    Conflict(String),
+    Status(StatusCode, String),
+    Parse(String),
+    // The MD5 of the data doesn't match the etag
+    ChecksumMismatch(String),
+    // A request exceeds one of the limits the cluster reports in /info
+    Limit(String)
     // there will probably be others
 }
 
@@ -21,7 +32,21 @@ impl fmt::Display for SwiftError {
             SwiftError::Http(ref err) => write!(
                 f, "Swift request HTTP error: {}", err),
             SwiftError::Auth(ref err) => write!(
-                f, "Swift request Auth error: {}", err)
+                f, "Swift request Auth error: {}", err),
+            SwiftError::Io(ref err) => write!(
+                f, "Swift response IO error: {}", err),
+            SwiftError::NotFound(ref s) => write!(
+                f, "Swift resource not found: {}", s),
+            SwiftError::Conflict(ref s) => write!(
+                f, "Swift request conflict: {}", s),
+            SwiftError::Status(ref status, ref s) => write!(
+                f, "Swift request failed with {}: {}", status, s),
+            SwiftError::Parse(ref s) => write!(
+                f, "Swift response parse error: {}", s),
+            SwiftError::ChecksumMismatch(ref s) => write!(
+                f, "Swift checksum mismatch: {}", s),
+            SwiftError::Limit(ref s) => write!(
+                f, "Swift limit exceeded: {}", s)
         }
     }
 }
@@ -30,14 +55,28 @@ impl error::Error for SwiftError {
     fn description(&self) -> &str {
         match *self {
             SwiftError::Http(ref err) => err.description(),
-            SwiftError::Auth(ref err) => err.description()
+            SwiftError::Auth(ref err) => err.description(),
+            SwiftError::Io(ref err) => err.description(),
+            SwiftError::NotFound(ref s) => s,
+            SwiftError::Conflict(ref s) => s,
+            SwiftError::Status(_, ref s) => s,
+            SwiftError::Parse(ref s) => s,
+            SwiftError::ChecksumMismatch(ref s) => s,
+            SwiftError::Limit(ref s) => s
         }
     }
 
     fn cause(&self) -> Option<&error::Error> {
         match *self {
             SwiftError::Http(ref err) => Some(err),
-            SwiftError::Auth(ref err) => Some(err)
+            SwiftError::Auth(ref err) => Some(err),
+            SwiftError::Io(ref err) => Some(err),
+            SwiftError::NotFound(_) => None,
+            SwiftError::Conflict(_) => None,
+            SwiftError::Status(_, _) => None,
+            SwiftError::Parse(_) => None,
+            SwiftError::ChecksumMismatch(_) => None,
+            SwiftError::Limit(_) => None
         }
     }
 }
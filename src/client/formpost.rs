@@ -0,0 +1,174 @@
+use chrono::{DateTime, Duration, UTC};
+use std::sync::Arc;
+
+use auth::sessions::Auth;
+use client::errors::SwiftError;
+use client::request::encode_path;
+use client::tempurl::{
+    TempUrlDigest, TempUrlExpiry, find_temp_url_key, hmac_hex, storage_url_path
+};
+
+/*
+ * Signed forms for uploading straight to Swift from a browser, using
+ * the formpost middleware
+ */
+
+// Swift's default limit on the size of a single object, 5 GiB
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 5 * 1024 * 1024 * 1024;
+
+// The action URL and hidden fields for the form
+#[derive(Clone, Debug, PartialEq)]
+pub struct FormPostFields {
+    pub url: String,
+    pub redirect: String,
+    pub max_file_size: u64,
+    pub max_file_count: u32,
+    pub expires: i64,
+    pub signature: String
+}
+
+impl FormPostFields {
+    // The hidden fields, as (name, value) pairs
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("redirect", self.redirect.clone()),
+            ("max_file_size", format!("{}", self.max_file_size)),
+            ("max_file_count", format!("{}", self.max_file_count)),
+            ("expires", format!("{}", self.expires)),
+            ("signature", self.signature.clone())
+        ]
+    }
+}
+
+pub struct FormPost<A> {
+    container: String,
+    // Uploaded files are named with this prefix
+    prefix: String,
+    key: Option<String>,
+    redirect: String,
+    max_file_size: u64,
+    max_file_count: u32,
+    expiry: TempUrlExpiry,
+    digest: TempUrlDigest,
+    auth: Arc<A>
+}
+
+impl<AS: Sized+Auth> FormPost<AS> {
+    pub fn new(auth: Arc<AS>, container: String, prefix: String) -> FormPost<AS> {
+        FormPost {
+            container: container,
+            prefix: prefix,
+            key: None,
+            redirect: String::new(),
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            max_file_count: 1,
+            expiry: TempUrlExpiry::In(Duration::hours(1)),
+            digest: TempUrlDigest::Sha256,
+            auth: auth
+        }
+    }
+
+    /*
+     * Without a key, the container's temp URL key is used, falling back
+     * to the account's
+     */
+    pub fn key<S: Into<String>>(mut self, key: S) -> FormPost<AS> {
+        self.key = Some(key.into());
+        self
+    }
+
+    // Where the browser is sent after the upload, if anywhere
+    pub fn redirect<S: Into<String>>(mut self, redirect: S) -> FormPost<AS> {
+        self.redirect = redirect.into();
+        self
+    }
+
+    // The largest file the form accepts, defaulting to DEFAULT_MAX_FILE_SIZE
+    pub fn max_file_size(mut self, max_file_size: u64) -> FormPost<AS> {
+        self.max_file_size = max_file_size;
+        self
+    }
+
+    pub fn max_file_count(mut self, max_file_count: u32) -> FormPost<AS> {
+        self.max_file_count = max_file_count;
+        self
+    }
+
+    pub fn expires_at(mut self, expires: DateTime<UTC>) -> FormPost<AS> {
+        self.expiry = TempUrlExpiry::At(expires);
+        self
+    }
+
+    pub fn expires_in(mut self, duration: Duration) -> FormPost<AS> {
+        self.expiry = TempUrlExpiry::In(duration);
+        self
+    }
+
+    pub fn digest(mut self, digest: TempUrlDigest) -> FormPost<AS> {
+        self.digest = digest;
+        self
+    }
+
+    /*
+     * The signature is the HMAC of
+     *   "{path}\n{redirect}\n{max_file_size}\n{max_file_count}\n{expires}"
+     */
+    pub fn fields(self) -> Result<FormPostFields, SwiftError> {
+        let key = match self.key {
+            Some(ref k) => k.clone(),
+            None => try!(find_temp_url_key(&self.auth, &self.container))
+        };
+        let (storage_url, storage_path) = try!(storage_url_path(self.auth.as_ref()));
+        let path = format!("{}/{}/{}", storage_path, self.container, self.prefix);
+        let expires = self.expiry.timestamp();
+        let body = format!("{}\n{}\n{}\n{}\n{}", path, self.redirect,
+                           self.max_file_size, self.max_file_count, expires);
+        Ok(FormPostFields {
+            url: format!("{}/{}",
+                         storage_url,
+                         encode_path(&format!("{}/{}", self.container, self.prefix))),
+            redirect: self.redirect,
+            max_file_size: self.max_file_size,
+            max_file_count: self.max_file_count,
+            expires: expires,
+            signature: hmac_hex(self.digest, &key, &body)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, UTC};
+    use std::sync::Arc;
+
+    use auth::sessions::StaticTokenAuth;
+    use client::formpost::{DEFAULT_MAX_FILE_SIZE, FormPost};
+    use client::tempurl::TempUrlDigest;
+
+    fn form_post(prefix: &str) -> FormPost<StaticTokenAuth> {
+        let auth = Arc::new(StaticTokenAuth::new(
+            String::from("token"), String::from("https://swift.example.com/v1/AUTH_account")));
+        FormPost::new(auth, String::from("c"), String::from(prefix))
+            .key("mykey")
+            .digest(TempUrlDigest::Sha1)
+            .expires_at(UTC.timestamp(1323479485, 0))
+    }
+
+    #[test]
+    fn fields_quote_names() {
+        let fields = form_post("up/100%41 x")
+            .redirect("https://example.com/done")
+            .max_file_size(1048576)
+            .max_file_count(5)
+            .fields().unwrap();
+        assert_eq!(fields.url, "https://swift.example.com/v1/AUTH_account/c/up/100%2541%20x");
+        // Signed with the unquoted path
+        assert_eq!(fields.signature, "3c9d5eebbf4fa2c4b900bb7ae82799a3ddb6eaa0");
+        assert_eq!(fields.expires, 1323479485);
+    }
+
+    #[test]
+    fn default_max_file_size() {
+        assert_eq!(form_post("up/").fields().unwrap().max_file_size, DEFAULT_MAX_FILE_SIZE);
+    }
+}
@@ -0,0 +1,349 @@
+use hyper::header::Headers;
+use hyper::method::Method;
+use reqwest::{Client, Response};
+use rustc_serialize::json::{self, Json};
+use std::sync::Arc;
+use url::Url;
+
+use auth::sessions::Auth;
+use client::errors::SwiftError;
+use client::request::{RunSwiftRequest, RunTypedSwiftRequest, SwiftRequestHeaders};
+use client::response::{check_status, read_body};
+
+/*
+ * Cluster capabilities, from the /info endpoint
+ */
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StoragePolicy {
+    pub name: String,
+    pub default: bool,
+    pub aliases: Vec<String>
+}
+
+// Limits of the core Swift API
+#[derive(Clone, Debug, PartialEq)]
+pub struct SwiftCapabilities {
+    pub version: Option<String>,
+    pub max_file_size: Option<u64>,
+    pub max_object_name_length: Option<u64>,
+    pub max_container_name_length: Option<u64>,
+    pub max_account_name_length: Option<u64>,
+    pub max_meta_name_length: Option<u64>,
+    pub max_meta_value_length: Option<u64>,
+    pub max_meta_count: Option<u64>,
+    pub max_meta_overall_size: Option<u64>,
+    pub max_header_size: Option<u64>,
+    pub container_listing_limit: Option<u64>,
+    pub account_listing_limit: Option<u64>,
+    pub policies: Vec<StoragePolicy>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SloCapabilities {
+    pub max_manifest_segments: Option<u64>,
+    pub max_manifest_size: Option<u64>,
+    pub min_segment_size: Option<u64>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DloCapabilities {
+    pub max_segments: Option<u64>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TempUrlCapabilities {
+    pub methods: Vec<String>,
+    pub allowed_digests: Vec<String>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BulkDeleteCapabilities {
+    pub max_deletes_per_request: Option<u64>,
+    pub max_failed_deletes: Option<u64>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SymlinkCapabilities {
+    pub symlink_loop_limit: Option<u64>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VersioningCapabilities {
+    // e.g. "x-versions-location" and "x-history-location"
+    pub allowed_flags: Vec<String>
+}
+
+/*
+ * Middleware that isn't installed is None. Anything not parsed here can
+ * be found in raw.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Capabilities {
+    pub swift: SwiftCapabilities,
+    pub slo: Option<SloCapabilities>,
+    pub dlo: Option<DloCapabilities>,
+    pub tempurl: Option<TempUrlCapabilities>,
+    pub bulk_delete: Option<BulkDeleteCapabilities>,
+    pub symlink: Option<SymlinkCapabilities>,
+    pub versioned_writes: Option<VersioningCapabilities>,
+    pub raw: json::Object
+}
+
+impl Capabilities {
+    pub fn from_json(info: &Json) -> Result<Capabilities, SwiftError> {
+        let raw = match info.as_object() {
+            Some(o) => o.clone(),
+            None => return Err(SwiftError::Parse(format!("Invalid /info response: {}", info)))
+        };
+        let swift = match info.find("swift") {
+            Some(s) => s,
+            None => return Err(SwiftError::Parse(String::from("/info has no swift section")))
+        };
+        Ok(Capabilities {
+            swift: SwiftCapabilities {
+                version: swift.find("version").and_then(|v| v.as_string()).map(String::from),
+                max_file_size: find_u64(swift, "max_file_size"),
+                max_object_name_length: find_u64(swift, "max_object_name_length"),
+                max_container_name_length: find_u64(swift, "max_container_name_length"),
+                max_account_name_length: find_u64(swift, "max_account_name_length"),
+                max_meta_name_length: find_u64(swift, "max_meta_name_length"),
+                max_meta_value_length: find_u64(swift, "max_meta_value_length"),
+                max_meta_count: find_u64(swift, "max_meta_count"),
+                max_meta_overall_size: find_u64(swift, "max_meta_overall_size"),
+                max_header_size: find_u64(swift, "max_header_size"),
+                container_listing_limit: find_u64(swift, "container_listing_limit"),
+                account_listing_limit: find_u64(swift, "account_listing_limit"),
+                policies: find_policies(swift)
+            },
+            slo: info.find("slo").map(|s| SloCapabilities {
+                max_manifest_segments: find_u64(s, "max_manifest_segments"),
+                max_manifest_size: find_u64(s, "max_manifest_size"),
+                min_segment_size: find_u64(s, "min_segment_size")
+            }),
+            dlo: info.find("dlo").map(|d| DloCapabilities {
+                max_segments: find_u64(d, "max_segments")
+            }),
+            tempurl: info.find("tempurl").map(|t| TempUrlCapabilities {
+                methods: find_strings(t, "methods"),
+                allowed_digests: find_strings(t, "allowed_digests")
+            }),
+            bulk_delete: info.find("bulk_delete").map(|b| BulkDeleteCapabilities {
+                max_deletes_per_request: find_u64(b, "max_deletes_per_request"),
+                max_failed_deletes: find_u64(b, "max_failed_deletes")
+            }),
+            symlink: info.find("symlink").map(|s| SymlinkCapabilities {
+                symlink_loop_limit: find_u64(s, "symlink_loop_limit")
+            }),
+            versioned_writes: info.find("versioned_writes").map(|v| VersioningCapabilities {
+                allowed_flags: find_strings(v, "allowed_flags")
+            }),
+            raw: raw
+        })
+    }
+
+    // Whether the named middleware is installed, e.g. "formpost"
+    pub fn has(&self, name: &str) -> bool {
+        self.raw.contains_key(name)
+    }
+
+    /*
+     * A segment size for large object uploads, as close to the one asked
+     * for as the cluster allows
+     */
+    pub fn segment_size(&self, preferred: u64) -> u64 {
+        let mut size = preferred;
+        if let Some(max) = self.swift.max_file_size {
+            if size > max {
+                size = max;
+            }
+        }
+        if let Some(min) = self.slo.as_ref().and_then(|s| s.min_segment_size) {
+            if size < min {
+                size = min;
+            }
+        }
+        size
+    }
+}
+
+fn find_u64(json: &Json, key: &str) -> Option<u64> {
+    json.find(key).and_then(|v| v.as_u64())
+}
+
+fn find_strings(json: &Json, key: &str) -> Vec<String> {
+    match json.find(key).and_then(|v| v.as_array()) {
+        Some(a) => a.iter().filter_map(|v| v.as_string()).map(String::from).collect(),
+        None => Vec::new()
+    }
+}
+
+fn find_policies(swift: &Json) -> Vec<StoragePolicy> {
+    let policies = match swift.find("policies").and_then(|p| p.as_array()) {
+        Some(p) => p,
+        None => return Vec::new()
+    };
+    policies.iter().filter_map(|p| {
+        p.find("name").and_then(|n| n.as_string()).map(|name| StoragePolicy {
+            name: String::from(name),
+            default: p.find("default").and_then(|d| d.as_boolean()).unwrap_or(false),
+            // A comma separated list
+            aliases: p.find("aliases").and_then(|a| a.as_string()).map(|a| {
+                a.split(',').map(|s| String::from(s.trim())).collect()
+            }).unwrap_or(Vec::new())
+        })
+    }).collect()
+}
+
+/*
+ * Get Info
+ */
+pub struct GetInfo<A> {
+    headers: Headers,
+    auth: Arc<A>
+}
+
+impl<AS: Sized+Auth> GetInfo<AS> {
+    pub fn new(auth: Arc<AS>) -> GetInfo<AS> {
+        GetInfo {
+            headers: Headers::new(),
+            auth: auth
+        }
+    }
+}
+
+impl<AS: Sized+Auth> SwiftRequestHeaders for GetInfo<AS> {
+    fn headers_mut(&mut self) -> &mut Headers {
+        &mut self.headers
+    }
+}
+
+impl<AS: Sized+Auth> RunSwiftRequest for GetInfo<AS> {
+    // /info doesn't need a token, so it's sent without one
+    fn run_request(self)
+            -> Result<Response, SwiftError> {
+        let info = try!(self.auth.session_info().map_err(SwiftError::Auth));
+        let url = try!(info_url(info.storage_url()));
+        let client = try!(Client::new().map_err(SwiftError::Http));
+        client.request(Method::Get, url).headers(self.headers).send()
+            .map_err(SwiftError::Http)
+    }
+}
+
+impl<AS: Sized+Auth> RunTypedSwiftRequest for GetInfo<AS> {
+    type Output = Capabilities;
+
+    fn run_typed_request(self)
+            -> Result<Capabilities, SwiftError> {
+        let mut resp = try!(check_status(try!(self.run_request())));
+        let body = try!(read_body(&mut resp));
+        match Json::from_str(&body) {
+            Ok(info) => Capabilities::from_json(&info),
+            Err(e) => Err(SwiftError::Parse(format!("{}", e)))
+        }
+    }
+}
+
+/*
+ * The storage URL is ".../v1/AUTH_account", and /info is served from
+ * the same place as the "v1"
+ */
+pub fn info_url(storage_url: &str) -> Result<Url, SwiftError> {
+    let mut url = match Url::parse(storage_url.trim_right_matches('/')) {
+        Ok(u) => u,
+        Err(e) => {
+            let err_msg = format!("Invalid storage URL {}: {}", storage_url, e);
+            return Err(SwiftError::Parse(err_msg))
+        }
+    };
+    let mut segments: Vec<String> = url.path().split('/')
+        .filter(|s| !s.is_empty()).map(String::from).collect();
+    let len = segments.len();
+    segments.truncate(if len >= 2 { len - 2 } else { 0 });
+    segments.push(String::from("info"));
+    url.set_path(&format!("/{}", segments.join("/")));
+    url.set_query(None);
+    Ok(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_serialize::json::Json;
+
+    use client::info::{Capabilities, StoragePolicy, info_url};
+
+    const INFO: &'static str = r#"{
+        "swift": {
+            "version": "2.15.1",
+            "max_file_size": 5368709122,
+            "account_listing_limit": 10000,
+            "policies": [
+                {"name": "gold", "aliases": "gold, fast", "default": true},
+                {"name": "silver"}
+            ]
+        },
+        "slo": {
+            "max_manifest_segments": 1000,
+            "max_manifest_size": 8388608,
+            "min_segment_size": 1048576
+        },
+        "formpost": {}
+    }"#;
+
+    fn info_path(storage_url: &str) -> Option<String> {
+        info_url(storage_url).ok().map(|u| u.to_string())
+    }
+
+    #[test]
+    fn info_urls() {
+        assert_eq!(info_path("https://swift.example.com/v1/AUTH_account"),
+                   Some(String::from("https://swift.example.com/info")));
+        assert_eq!(info_path("https://swift.example.com/v1/AUTH_account/"),
+                   Some(String::from("https://swift.example.com/info")));
+        assert_eq!(info_path("https://example.com:8080/swift/v1/AUTH_account?x=1"),
+                   Some(String::from("https://example.com:8080/swift/info")));
+        assert_eq!(info_path("not a url"), None);
+    }
+
+    #[test]
+    fn parse_limits() {
+        let caps = Capabilities::from_json(&Json::from_str(INFO).unwrap()).unwrap();
+        assert_eq!(caps.swift.version, Some(String::from("2.15.1")));
+        assert_eq!(caps.swift.max_file_size, Some(5368709122));
+        assert_eq!(caps.swift.account_listing_limit, Some(10000));
+        assert_eq!(caps.swift.max_meta_count, None);
+        assert_eq!(caps.swift.policies, vec![
+            StoragePolicy {
+                name: String::from("gold"),
+                default: true,
+                aliases: vec![String::from("gold"), String::from("fast")]
+            },
+            StoragePolicy {
+                name: String::from("silver"),
+                default: false,
+                aliases: vec![]
+            }
+        ]);
+        let slo = caps.slo.clone().unwrap();
+        assert_eq!(slo.max_manifest_segments, Some(1000));
+        assert_eq!(slo.max_manifest_size, Some(8388608));
+        assert_eq!(slo.min_segment_size, Some(1048576));
+        assert_eq!(caps.dlo, None);
+        assert!(caps.has("formpost"));
+        assert!(!caps.has("tempurl"));
+    }
+
+    #[test]
+    fn segment_size_limits() {
+        let caps = Capabilities::from_json(&Json::from_str(INFO).unwrap()).unwrap();
+        assert_eq!(caps.segment_size(100), 1048576);
+        assert_eq!(caps.segment_size(10 * 1048576), 10 * 1048576);
+        assert_eq!(caps.segment_size(10000000000), 5368709122);
+    }
+
+    #[test]
+    fn missing_swift_section() {
+        assert!(Capabilities::from_json(&Json::from_str(r#"{"slo": {}}"#).unwrap()).is_err());
+        assert!(Capabilities::from_json(&Json::from_str("[]").unwrap()).is_err());
+    }
+}
@@ -0,0 +1,489 @@
+use chrono::UTC;
+use hyper::header::{ContentLength, Headers};
+use rustc_serialize::json::{Json, Object};
+use reqwest::Body;
+use std::io::{self, Cursor, Read};
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+
+use auth::sessions::Auth;
+use client::checksum::{check_md5, md5_hex, unquote_etag};
+use client::errors::SwiftError;
+use client::info::Capabilities;
+use client::object::ObjectManifest;
+use client::parallel::map_ordered;
+use client::request::{
+    DeleteObject, PutContainer, PutObject, RunSwiftRequest, RunTypedSwiftRequest,
+    SwiftRequestHeaders
+};
+use client::response::{check_status, header_string};
+use client::retry::with_retries;
+
+/*
+ * Segmented uploads for objects larger than a single PUT allows
+ */
+
+// 100 MiB, since each segment is held in memory while it is uploaded
+pub const DEFAULT_SEGMENT_SIZE: u64 = 100 * 1024 * 1024;
+pub const DEFAULT_SEGMENT_RETRIES: u32 = 3;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SegmentInfo {
+    // The segment's path, as "container/object"
+    pub path: String,
+    pub etag: String,
+    pub size_bytes: u64
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LargeObjectUpload {
+    // The etag of the manifest PUT, which for a DLO is just that of
+    // the empty manifest object
+    pub etag: Option<String>,
+    pub segments: Vec<SegmentInfo>
+}
+
+/*
+ * The parts that differ between static and dynamic large objects
+ */
+pub trait ManifestKind {
+    // Used in the default segment prefix
+    fn name() -> &'static str;
+
+    // The cluster's limit on the number of segments, if it has one
+    fn max_segments(caps: &Capabilities) -> Option<u64>;
+
+    // PUT the manifest once the segments are uploaded, returning its etag
+    fn put_manifest<AS: Sized+Auth>(
+        auth: &Arc<AS>, container: String, object: String, headers: Headers,
+        segment_container: &str, segment_prefix: &str, segments: &Vec<SegmentInfo>
+    ) -> Result<Option<String>, SwiftError>;
+}
+
+pub struct Slo;
+
+impl ManifestKind for Slo {
+    fn name() -> &'static str {
+        "slo"
+    }
+
+    fn max_segments(caps: &Capabilities) -> Option<u64> {
+        caps.slo.as_ref().and_then(|s| s.max_manifest_segments)
+    }
+
+    fn put_manifest<AS: Sized+Auth>(
+        auth: &Arc<AS>, container: String, object: String, headers: Headers,
+        _segment_container: &str, _segment_prefix: &str, segments: &Vec<SegmentInfo>
+    ) -> Result<Option<String>, SwiftError> {
+        // Swift won't accept a manifest without any segments
+        if segments.is_empty() {
+            let resp = try!(check_status(try!(
+                PutObject::new(auth.clone(), container, object, "")
+                    .headers(headers).run_request())));
+            return Ok(header_string(resp.headers(), "Etag"))
+        }
+
+        let manifest = slo_manifest(segments);
+        let resp = try!(check_status(try!(
+            PutObject::new(auth.clone(), container.clone(), object.clone(), manifest)
+                .multipart_manifest_put(true)
+                .headers(headers)
+                .replayable()
+                .run_request())));
+        let etag = header_string(resp.headers(), "Etag");
+        if let Err(e) = check_manifest_etag(&etag, segments) {
+            // The segments are about to be deleted, which would break it
+            let name = format!("{}/{}", container, object);
+            delete_objects(auth, &container, &[object]);
+            warn!("Deleted manifest {} with the wrong etag", name);
+            return Err(e)
+        }
+        Ok(etag)
+    }
+}
+
+pub struct Dlo;
+
+impl ManifestKind for Dlo {
+    fn name() -> &'static str {
+        "dlo"
+    }
+
+    fn max_segments(caps: &Capabilities) -> Option<u64> {
+        caps.dlo.as_ref().and_then(|d| d.max_segments)
+    }
+
+    // The manifest is an empty object pointing at the segments
+    fn put_manifest<AS: Sized+Auth>(
+        auth: &Arc<AS>, container: String, object: String, headers: Headers,
+        segment_container: &str, segment_prefix: &str, _segments: &Vec<SegmentInfo>
+    ) -> Result<Option<String>, SwiftError> {
+        let manifest = ObjectManifest::dynamic_header_value(
+            segment_container, segment_prefix);
+        let resp = try!(check_status(try!(
+            PutObject::new(auth.clone(), container, object, "")
+                .headers(headers)
+                .raw_header("X-Object-Manifest", manifest)
+                .run_request())));
+        Ok(header_string(resp.headers(), "Etag"))
+    }
+}
+
+pub type SloUpload<A, R> = SegmentedUpload<A, R, Slo>;
+pub type DloUpload<A, R> = SegmentedUpload<A, R, Dlo>;
+
+/*
+ * Static or Dynamic Large Object upload
+ */
+pub struct SegmentedUpload<A, R: Read, K: ManifestKind> {
+    container: String,
+    object: String,
+    source: R,
+    segment_container: Option<String>,
+    segment_prefix: Option<String>,
+    segment_size: u64,
+    max_segments: Option<u64>,
+    source_length: Option<u64>,
+    retries: u32,
+    concurrency: usize,
+    create_segment_container: bool,
+    headers: Headers,
+    kind: PhantomData<K>,
+    auth: Arc<A>
+}
+
+impl<AS: Sized+Auth, R: Read, K: ManifestKind> SegmentedUpload<AS, R, K> {
+    pub fn new(
+        auth: Arc<AS>, container: String, object: String, source: R
+    ) -> SegmentedUpload<AS, R, K> {
+        SegmentedUpload {
+            container: container,
+            object: object,
+            source: source,
+            segment_container: None,
+            segment_prefix: None,
+            segment_size: DEFAULT_SEGMENT_SIZE,
+            max_segments: None,
+            source_length: None,
+            retries: DEFAULT_SEGMENT_RETRIES,
+            concurrency: 1,
+            create_segment_container: true,
+            headers: Headers::new(),
+            kind: PhantomData,
+            auth: auth
+        }
+    }
+
+    // Defaults to "{container}_segments"
+    pub fn segment_container<S: Into<String>>(
+        mut self, segment_container: S
+    ) -> SegmentedUpload<AS, R, K> {
+        self.segment_container = Some(segment_container.into());
+        self
+    }
+
+    /*
+     * Defaults to "{object}/slo/{timestamp}/{segment_size}/" ("dlo" for
+     * dynamic large objects). Any other objects under a DLO's prefix
+     * become part of the large object, so it should be unique to this
+     * upload.
+     */
+    pub fn segment_prefix<S: Into<String>>(
+        mut self, segment_prefix: S
+    ) -> SegmentedUpload<AS, R, K> {
+        self.segment_prefix = Some(segment_prefix.into());
+        self
+    }
+
+    pub fn segment_size(mut self, segment_size: u64) -> SegmentedUpload<AS, R, K> {
+        self.segment_size = segment_size;
+        self
+    }
+
+    /*
+     * Fit the segment size and count to the cluster's limits, as
+     * returned by get_info
+     */
+    pub fn capabilities(mut self, caps: &Capabilities) -> SegmentedUpload<AS, R, K> {
+        self.segment_size = caps.segment_size(self.segment_size);
+        self.max_segments = K::max_segments(caps);
+        self
+    }
+
+    /*
+     * The length of the source, if it's known (e.g. for a file), so that
+     * an upload needing more segments than the cluster allows fails
+     * before anything is uploaded. Otherwise it fails once the limit is
+     * reached, and the segments already uploaded are deleted.
+     */
+    pub fn source_length(mut self, source_length: u64) -> SegmentedUpload<AS, R, K> {
+        self.source_length = Some(source_length);
+        self
+    }
+
+    /*
+     * How many times to retry each segment before giving up. Only errors
+     * that might not happen again are retried, with a growing delay.
+     */
+    pub fn retries(mut self, retries: u32) -> SegmentedUpload<AS, R, K> {
+        self.retries = retries;
+        self
+    }
+
+    /*
+     * How many segments to upload at once. Up to one more than this many
+     * segments are held in memory at a time.
+     */
+    pub fn concurrency(mut self, concurrency: usize) -> SegmentedUpload<AS, R, K> {
+        self.concurrency = concurrency;
+        self
+    }
+
+    pub fn create_segment_container(
+        mut self, create_segment_container: bool
+    ) -> SegmentedUpload<AS, R, K> {
+        self.create_segment_container = create_segment_container;
+        self
+    }
+}
+
+// Headers set here are sent with the manifest PUT
+impl<AS: Sized+Auth, R: Read, K: ManifestKind> SwiftRequestHeaders
+        for SegmentedUpload<AS, R, K> {
+    fn headers_mut(&mut self) -> &mut Headers {
+        &mut self.headers
+    }
+}
+
+impl<AS: Sized+Auth+Send+Sync+'static, R: Read, K: ManifestKind> RunTypedSwiftRequest
+        for SegmentedUpload<AS, R, K> {
+    type Output = LargeObjectUpload;
+
+    fn run_typed_request(mut self)
+            -> Result<LargeObjectUpload, SwiftError> {
+        // Nothing would be read from the source, so it would be lost
+        if self.segment_size == 0 {
+            let err_msg = format!("Invalid segment size 0 for {}/{}",
+                                  self.container, self.object);
+            return Err(SwiftError::Io(io::Error::new(io::ErrorKind::InvalidInput, err_msg)))
+        }
+        if let (Some(length), Some(max)) = (self.source_length, self.max_segments) {
+            let needed = segment_count(length, self.segment_size);
+            if needed > max {
+                let err_msg = format!(
+                    "{} bytes needs {} segments of {} bytes, the cluster allows {}",
+                    length, needed, self.segment_size, max);
+                return Err(SwiftError::Limit(err_msg))
+            }
+        }
+        let segment_container = self.segment_container.clone().unwrap_or(
+            format!("{}_segments", self.container));
+        let segment_prefix = self.segment_prefix.clone().unwrap_or(
+            format!("{}/{}/{}/{}/", self.object, K::name(),
+                    UTC::now().timestamp(), self.segment_size));
+        if self.create_segment_container {
+            try!(PutContainer::new(self.auth.clone(), segment_container.clone())
+                 .run_typed_request());
+        };
+
+        let options = SegmentOptions {
+            container: segment_container.clone(),
+            prefix: segment_prefix.clone(),
+            segment_size: self.segment_size,
+            max_segments: self.max_segments,
+            retries: self.retries,
+            concurrency: self.concurrency
+        };
+        let segments = try!(upload_segments(&self.auth, &mut self.source, options));
+        let result = K::put_manifest(
+            &self.auth, self.container, self.object, self.headers,
+            &segment_container, &segment_prefix, &segments);
+        let etag = match result {
+            Ok(etag) => etag,
+            Err(e) => {
+                // Nothing refers to the segments without a manifest
+                let names: Vec<String> = (0..segments.len()).map(
+                    |i| segment_name(&segment_prefix, i)).collect();
+                delete_objects(&self.auth, &segment_container, &names);
+                return Err(e)
+            }
+        };
+        Ok(LargeObjectUpload {
+            etag: etag,
+            segments: segments
+        })
+    }
+}
+
+/*
+ * Helpers shared by the large object uploaders
+ */
+
+struct SegmentOptions {
+    container: String,
+    prefix: String,
+    segment_size: u64,
+    max_segments: Option<u64>,
+    retries: u32,
+    concurrency: usize
+}
+
+/*
+ * Read and upload fixed size segments until the source is exhausted. If
+ * any segment fails, the ones already uploaded are deleted again.
+ */
+fn upload_segments<AS: Sized+Auth+Send+Sync+'static, R: Read>(
+    auth: &Arc<AS>, source: &mut R, options: SegmentOptions
+) -> Result<Vec<SegmentInfo>, SwiftError> {
+    let segments = SegmentReader {
+        source: source,
+        segment_size: options.segment_size,
+        max_segments: options.max_segments,
+        count: 0
+    };
+    let uploaded = Arc::new(Mutex::new(Vec::new()));
+    let result = {
+        let auth = auth.clone();
+        let uploaded = uploaded.clone();
+        let container = options.container.clone();
+        let prefix = options.prefix;
+        let retries = options.retries;
+        map_ordered(segments, options.concurrency, move |index, data| {
+            let name = segment_name(&prefix, index);
+            let segment = try!(upload_segment(&auth, &container, &name, data, retries));
+            if let Ok(mut u) = uploaded.lock() {
+                u.push(name);
+            }
+            Ok(segment)
+        })
+    };
+    if result.is_err() {
+        if let Ok(u) = uploaded.lock() {
+            delete_objects(auth, &options.container, &u);
+        }
+    }
+    result
+}
+
+// Best effort, since this is only done when the upload has already failed
+fn delete_objects<AS: Sized+Auth>(auth: &Arc<AS>, container: &str, names: &[String]) {
+    for name in names {
+        let result = DeleteObject::new(
+            auth.clone(), String::from(container), name.clone()
+        ).run_typed_request();
+        match result {
+            Ok(()) | Err(SwiftError::NotFound(_)) => {},
+            Err(e) => warn!("Failed to delete {}/{}: {}", container, name, e)
+        };
+    }
+}
+
+// Yields the segments of the source, until it is exhausted
+struct SegmentReader<'a, R: 'a + Read> {
+    source: &'a mut R,
+    segment_size: u64,
+    max_segments: Option<u64>,
+    count: u64
+}
+
+impl<'a, R: Read> Iterator for SegmentReader<'a, R> {
+    type Item = Result<Vec<u8>, SwiftError>;
+
+    fn next(&mut self) -> Option<Result<Vec<u8>, SwiftError>> {
+        let mut data = Vec::new();
+        match self.source.by_ref().take(self.segment_size).read_to_end(&mut data) {
+            Ok(0) => None,
+            Ok(_) => {
+                self.count += 1;
+                match self.max_segments {
+                    // Stop before uploading segments that can't be used
+                    Some(max) if self.count > max => {
+                        let err_msg = format!(
+                            "Too many segments, the cluster allows {}", max);
+                        Some(Err(SwiftError::Limit(err_msg)))
+                    },
+                    _ => Some(Ok(data))
+                }
+            },
+            Err(e) => Some(Err(SwiftError::Io(e)))
+        }
+    }
+}
+
+fn segment_count(length: u64, segment_size: u64) -> u64 {
+    (length + segment_size - 1) / segment_size
+}
+
+// Zero padded, so that the segments list in order
+fn segment_name(prefix: &str, index: usize) -> String {
+    format!("{}{:08}", prefix, index)
+}
+
+// A segment's data, shared between attempts rather than copied for each
+#[derive(Clone)]
+struct SegmentData(Arc<Vec<u8>>);
+
+impl AsRef<[u8]> for SegmentData {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+// Sent with a Content-Length, since the reader's size isn't known
+impl From<SegmentData> for Body {
+    fn from(data: SegmentData) -> Body {
+        Body::new(Cursor::new(data))
+    }
+}
+
+/*
+ * Upload a single segment, sending its MD5 so that Swift rejects it if
+ * it was corrupted on the way
+ */
+fn upload_segment<AS: Sized+Auth>(
+    auth: &Arc<AS>, container: &str, name: &str, data: Vec<u8>, retries: u32
+) -> Result<SegmentInfo, SwiftError> {
+    let size_bytes = data.len() as u64;
+    let data = SegmentData(Arc::new(data));
+    let description = format!("Segment {}/{} upload", container, name);
+    let resp = try!(with_retries(retries, &description, || {
+        PutObject::new(
+            auth.clone(), String::from(container), String::from(name), data.clone()
+        ).checksum()
+            .replayable()
+            .header(ContentLength(size_bytes))
+            .run_request().and_then(check_status)
+    }));
+    // The returned etag has already been checked against the data
+    Ok(SegmentInfo {
+        path: format!("{}/{}", container, name),
+        etag: header_string(resp.headers(), "Etag").map(
+            |e| String::from(unquote_etag(&e))).unwrap_or(String::new()),
+        size_bytes: size_bytes
+    })
+}
+
+fn slo_manifest(segments: &Vec<SegmentInfo>) -> String {
+    let entries = segments.iter().map(|s| {
+        let mut entry = Object::new();
+        entry.insert(String::from("path"), Json::String(format!("/{}", s.path)));
+        entry.insert(String::from("etag"), Json::String(s.etag.clone()));
+        entry.insert(String::from("size_bytes"), Json::U64(s.size_bytes));
+        Json::Object(entry)
+    }).collect();
+    Json::Array(entries).to_string()
+}
+
+/*
+ * The etag of an SLO is the MD5 of its segments' etags, so checking it
+ * confirms that Swift built the manifest from the segments we uploaded
+ */
+fn check_manifest_etag(etag: &Option<String>, segments: &Vec<SegmentInfo>)
+    -> Result<(), SwiftError>
+{
+    let concatenated: String = segments.iter().map(|s| &s.etag[..]).collect();
+    match *etag {
+        Some(ref e) => check_md5("Manifest", e, &md5_hex(concatenated.as_bytes())),
+        None => Err(SwiftError::Parse(String::from("Manifest PUT returned no etag")))
+    }
+}
@@ -0,0 +1,444 @@
+use chrono::{DateTime, UTC};
+use hyper::header::Headers;
+use reqwest::Response;
+use rustc_serialize::json;
+use std::collections::BTreeMap;
+use xml::reader::{EventReader, XmlEvent};
+
+use client::errors::SwiftError;
+use client::metadata::{Metadata, MetadataKind};
+use client::request::Format;
+use client::response::{header_parse, header_string, parse_listing_time, read_body};
+
+/*
+ * Typed account and container listings
+ */
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContainerInfo {
+    pub name: String,
+    // Only the name is included in plain text listings
+    pub count: Option<u64>,
+    pub bytes: Option<u64>,
+    pub last_modified: Option<DateTime<UTC>>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjectInfo {
+    pub name: String,
+    // Only the name is included in plain text listings
+    pub hash: Option<String>,
+    pub bytes: Option<u64>,
+    pub content_type: Option<String>,
+    pub last_modified: Option<DateTime<UTC>>
+}
+
+/*
+ * Listings with a delimiter return the common prefixes as subdirs
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum ContainerEntry {
+    Container(ContainerInfo),
+    Subdir(String)
+}
+
+impl ContainerEntry {
+    pub fn name(&self) -> &str {
+        match *self {
+            ContainerEntry::Container(ref c) => &c.name,
+            ContainerEntry::Subdir(ref s) => s
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ObjectEntry {
+    Object(ObjectInfo),
+    Subdir(String)
+}
+
+impl ObjectEntry {
+    pub fn name(&self) -> &str {
+        match *self {
+            ObjectEntry::Object(ref o) => &o.name,
+            ObjectEntry::Subdir(ref s) => s
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccountStats {
+    pub container_count: u64,
+    pub object_count: u64,
+    pub bytes_used: u64,
+    pub quota_bytes: Option<u64>,
+    pub metadata: Metadata
+}
+
+impl AccountStats {
+    pub fn from_headers(headers: &Headers) -> Result<AccountStats, SwiftError> {
+        Ok(AccountStats {
+            container_count: try!(
+                header_parse(headers, "X-Account-Container-Count")).unwrap_or(0),
+            object_count: try!(
+                header_parse(headers, "X-Account-Object-Count")).unwrap_or(0),
+            bytes_used: try!(
+                header_parse(headers, "X-Account-Bytes-Used")).unwrap_or(0),
+            quota_bytes: try!(header_parse(headers, "X-Account-Meta-Quota-Bytes")),
+            metadata: Metadata::from_headers(headers, MetadataKind::Account)
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContainerStats {
+    pub object_count: u64,
+    pub bytes_used: u64,
+    pub storage_policy: Option<String>,
+    pub read_acl: Option<String>,
+    pub write_acl: Option<String>,
+    pub metadata: Metadata
+}
+
+impl ContainerStats {
+    pub fn from_headers(headers: &Headers) -> Result<ContainerStats, SwiftError> {
+        Ok(ContainerStats {
+            object_count: try!(
+                header_parse(headers, "X-Container-Object-Count")).unwrap_or(0),
+            bytes_used: try!(
+                header_parse(headers, "X-Container-Bytes-Used")).unwrap_or(0),
+            storage_policy: header_string(headers, "X-Storage-Policy"),
+            read_acl: header_string(headers, "X-Container-Read"),
+            write_acl: header_string(headers, "X-Container-Write"),
+            metadata: Metadata::from_headers(headers, MetadataKind::Container)
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccountListing {
+    pub stats: AccountStats,
+    pub containers: Vec<ContainerEntry>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContainerListing {
+    pub stats: ContainerStats,
+    pub objects: Vec<ObjectEntry>
+}
+
+/*
+ * Parse the listing responses
+ */
+
+pub fn parse_account_listing(mut resp: Response, format: Format,
+                             delimiter: &Option<String>)
+    -> Result<AccountListing, SwiftError>
+{
+    let stats = try!(AccountStats::from_headers(resp.headers()));
+    let body = try!(read_body(&mut resp));
+    let records = try!(parse_records(&body, format, delimiter));
+    let mut containers = Vec::new();
+    for record in records {
+        containers.push(try!(container_entry(record)));
+    }
+    Ok(AccountListing {
+        stats: stats,
+        containers: containers
+    })
+}
+
+pub fn parse_container_listing(mut resp: Response, format: Format,
+                               delimiter: &Option<String>)
+    -> Result<ContainerListing, SwiftError>
+{
+    let stats = try!(ContainerStats::from_headers(resp.headers()));
+    let body = try!(read_body(&mut resp));
+    let records = try!(parse_records(&body, format, delimiter));
+    let mut objects = Vec::new();
+    for record in records {
+        objects.push(try!(object_entry(record)));
+    }
+    Ok(ContainerListing {
+        stats: stats,
+        objects: objects
+    })
+}
+
+/*
+ * Each format is first parsed into a list of flat records, so the
+ * conversion into typed entries can be shared
+ */
+struct Record {
+    subdir: Option<String>,
+    fields: BTreeMap<String, String>
+}
+
+impl Record {
+    fn new() -> Record {
+        Record {
+            subdir: None,
+            fields: BTreeMap::new()
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<String> {
+        self.fields.get(key).cloned()
+    }
+
+    fn get_u64(&self, key: &str) -> Result<Option<u64>, SwiftError> {
+        match self.fields.get(key) {
+            Some(v) => match v.parse::<u64>() {
+                Ok(n) => Ok(Some(n)),
+                Err(_) => {
+                    let err_msg = format!("Invalid listing value for {}: {}", key, v);
+                    Err(SwiftError::Parse(err_msg))
+                }
+            },
+            None => Ok(None)
+        }
+    }
+
+    fn name(&self) -> Result<String, SwiftError> {
+        match self.get("name") {
+            Some(n) => Ok(n),
+            None => Err(SwiftError::Parse(String::from("Listing entry without a name")))
+        }
+    }
+}
+
+fn container_entry(record: Record) -> Result<ContainerEntry, SwiftError> {
+    if let Some(ref s) = record.subdir {
+        return Ok(ContainerEntry::Subdir(s.clone()))
+    }
+    Ok(ContainerEntry::Container(ContainerInfo {
+        name: try!(record.name()),
+        count: try!(record.get_u64("count")),
+        bytes: try!(record.get_u64("bytes")),
+        last_modified: record.get("last_modified").and_then(
+            |t| parse_listing_time(&t))
+    }))
+}
+
+fn object_entry(record: Record) -> Result<ObjectEntry, SwiftError> {
+    if let Some(ref s) = record.subdir {
+        return Ok(ObjectEntry::Subdir(s.clone()))
+    }
+    Ok(ObjectEntry::Object(ObjectInfo {
+        name: try!(record.name()),
+        hash: record.get("hash"),
+        bytes: try!(record.get_u64("bytes")),
+        content_type: record.get("content_type"),
+        last_modified: record.get("last_modified").and_then(
+            |t| parse_listing_time(&t))
+    }))
+}
+
+fn parse_records(body: &str, format: Format, delimiter: &Option<String>)
+    -> Result<Vec<Record>, SwiftError>
+{
+    // Empty listings may come back as 204 No Content
+    if body.trim().is_empty() {
+        return Ok(Vec::new())
+    }
+    match format {
+        Format::JSON => parse_json_records(body),
+        Format::XML => parse_xml_records(body),
+        Format::Plain => Ok(parse_plain_records(body, delimiter))
+    }
+}
+
+fn parse_json_records(body: &str) -> Result<Vec<Record>, SwiftError> {
+    let listing = match json::Json::from_str(body) {
+        Ok(j) => j,
+        Err(e) => return Err(SwiftError::Parse(format!("{}", e)))
+    };
+    let entries = match listing.as_array() {
+        Some(a) => a,
+        None => return Err(SwiftError::Parse(String::from("Listing is not a list")))
+    };
+    let mut records = Vec::new();
+    for entry in entries {
+        let obj = match entry.as_object() {
+            Some(o) => o,
+            None => return Err(SwiftError::Parse(format!("Invalid listing entry: {}", entry)))
+        };
+        let mut record = Record::new();
+        for (key, value) in obj {
+            let v = match *value {
+                json::Json::String(ref s) => s.clone(),
+                json::Json::Null => continue,
+                ref other => format!("{}", other)
+            };
+            if key == "subdir" {
+                record.subdir = Some(v);
+            } else {
+                record.fields.insert(key.clone(), v);
+            }
+        }
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/*
+ * XML listings look like
+ *   <container name="c"><object><name>o</name>...</object>
+ *     <subdir name="d/"><name>d/</name></subdir></container>
+ * and account listings the same, with <account> and <container>
+ */
+fn parse_xml_records(body: &str) -> Result<Vec<Record>, SwiftError> {
+    let parser = EventReader::new(body.as_bytes());
+    let mut records = Vec::new();
+    let mut record: Option<Record> = None;
+    let mut field: Option<String> = None;
+    let mut depth = 0;
+    for event in parser {
+        match event {
+            Ok(XmlEvent::StartElement { name, attributes, .. }) => {
+                depth += 1;
+                if depth == 2 {
+                    let mut r = Record::new();
+                    if name.local_name == "subdir" {
+                        r.subdir = attributes.iter().find(
+                            |a| a.name.local_name == "name"
+                        ).map(|a| a.value.clone());
+                    }
+                    record = Some(r);
+                } else if depth == 3 {
+                    field = Some(name.local_name);
+                }
+            },
+            Ok(XmlEvent::Characters(text)) => {
+                if let (Some(r), Some(f)) = (record.as_mut(), field.as_ref()) {
+                    r.fields.entry(f.clone()).or_insert(String::new()).push_str(&text);
+                }
+            },
+            Ok(XmlEvent::EndElement { .. }) => {
+                if depth == 2 {
+                    if let Some(r) = record.take() {
+                        records.push(r);
+                    }
+                } else if depth == 3 {
+                    field = None;
+                }
+                depth -= 1;
+            },
+            Ok(_) => (),
+            Err(e) => return Err(SwiftError::Parse(format!("{}", e)))
+        }
+    }
+    Ok(records)
+}
+
+/*
+ * Plain listings are one name per line. With a delimiter, the names
+ * ending in it are subdirs.
+ */
+fn parse_plain_records(body: &str, delimiter: &Option<String>) -> Vec<Record> {
+    body.lines().filter(|l| !l.is_empty()).map(|line| {
+        let mut record = Record::new();
+        match *delimiter {
+            Some(ref d) if line.ends_with(&d[..]) => {
+                record.subdir = Some(String::from(line));
+            },
+            _ => {
+                record.fields.insert(String::from("name"), String::from(line));
+            }
+        };
+        record
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, UTC};
+
+    use client::listing::{
+        ContainerEntry, ContainerInfo, ObjectEntry, ObjectInfo, container_entry,
+        object_entry, parse_records
+    };
+    use client::request::Format;
+
+    fn object_entries(body: &str, format: Format, delimiter: Option<String>)
+        -> Vec<ObjectEntry>
+    {
+        parse_records(body, format, &delimiter).unwrap().into_iter()
+            .map(|r| object_entry(r).unwrap()).collect()
+    }
+
+    #[test]
+    fn xml_listing() {
+        let body = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container name="c">
+  <object>
+    <name>a &amp; b</name>
+    <hash>d41d8cd98f00b204e9800998ecf8427e</hash>
+    <bytes>0</bytes>
+    <content_type>text/plain</content_type>
+    <last_modified>2017-03-01T12:30:15.123450</last_modified>
+  </object>
+  <subdir name="dir/"><name>dir/</name></subdir>
+</container>"#;
+        assert_eq!(object_entries(body, Format::XML, Some(String::from("/"))), vec![
+            ObjectEntry::Object(ObjectInfo {
+                name: String::from("a & b"),
+                hash: Some(String::from("d41d8cd98f00b204e9800998ecf8427e")),
+                bytes: Some(0),
+                content_type: Some(String::from("text/plain")),
+                last_modified: Some(
+                    UTC.ymd(2017, 3, 1).and_hms_micro(12, 30, 15, 123450))
+            }),
+            ObjectEntry::Subdir(String::from("dir/"))
+        ]);
+    }
+
+    #[test]
+    fn plain_listing() {
+        let body = "a\ndir/\nz\n";
+        let object = |name: &str| ObjectEntry::Object(ObjectInfo {
+            name: String::from(name),
+            hash: None,
+            bytes: None,
+            content_type: None,
+            last_modified: None
+        });
+        assert_eq!(object_entries(body, Format::Plain, Some(String::from("/"))), vec![
+            object("a"),
+            ObjectEntry::Subdir(String::from("dir/")),
+            object("z")
+        ]);
+        // Without a delimiter there are no subdirs
+        assert_eq!(object_entries(body, Format::Plain, None), vec![
+            object("a"), object("dir/"), object("z")
+        ]);
+    }
+
+    #[test]
+    fn account_listing_subdirs() {
+        let body = r#"[
+            {"name": "logs-2017", "count": 3, "bytes": 1024,
+             "last_modified": "2017-03-01T12:30:15.123450"},
+            {"subdir": "photos-"}
+        ]"#;
+        let entries: Vec<ContainerEntry> = parse_records(
+            body, Format::JSON, &Some(String::from("-"))
+        ).unwrap().into_iter().map(|r| container_entry(r).unwrap()).collect();
+        assert_eq!(entries, vec![
+            ContainerEntry::Container(ContainerInfo {
+                name: String::from("logs-2017"),
+                count: Some(3),
+                bytes: Some(1024),
+                last_modified: Some(
+                    UTC.ymd(2017, 3, 1).and_hms_micro(12, 30, 15, 123450))
+            }),
+            ContainerEntry::Subdir(String::from("photos-"))
+        ]);
+    }
+
+    #[test]
+    fn empty_listing() {
+        assert_eq!(object_entries("", Format::XML, None), Vec::<ObjectEntry>::new());
+        assert_eq!(object_entries("\n", Format::Plain, None), Vec::<ObjectEntry>::new());
+    }
+}
@@ -0,0 +1,223 @@
+use hyper::header::Headers;
+use std::collections::BTreeMap;
+use std::collections::btree_map;
+use std::fmt::Display;
+use std::iter::FromIterator;
+use url::percent_encoding::{SIMPLE_ENCODE_SET, percent_decode, percent_encode};
+
+define_encode_set! {
+    // '%' is quoted too, so values that contain one survive decoding
+    pub METADATA_ENCODE_SET = [SIMPLE_ENCODE_SET] | {'%'}
+}
+
+/*
+ * User metadata for accounts, containers and objects
+ */
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MetadataKind {
+    Account,
+    Container,
+    Object
+}
+
+impl MetadataKind {
+    fn prefix(&self) -> &'static str {
+        match *self {
+            MetadataKind::Account => "X-Account-Meta-",
+            MetadataKind::Container => "X-Container-Meta-",
+            MetadataKind::Object => "X-Object-Meta-"
+        }
+    }
+
+    fn remove_prefix(&self) -> &'static str {
+        match *self {
+            MetadataKind::Account => "X-Remove-Account-Meta-",
+            MetadataKind::Container => "X-Remove-Container-Meta-",
+            MetadataKind::Object => "X-Remove-Object-Meta-"
+        }
+    }
+}
+
+/*
+ * Header names are case-insensitive, so the names are kept in lower
+ * case, without the X-*-Meta- prefix
+ */
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metadata {
+    entries: BTreeMap<String, String>
+}
+
+impl Metadata {
+    pub fn new() -> Metadata {
+        Metadata {
+            entries: BTreeMap::new()
+        }
+    }
+
+    pub fn insert<K: AsRef<str>, V: Into<String>>(
+        &mut self, name: K, value: V
+    ) -> Option<String> {
+        self.entries.insert(name.as_ref().to_lowercase(), value.into())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&String> {
+        self.entries.get(&name.to_lowercase())
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        self.entries.remove(&name.to_lowercase())
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.entries.contains_key(&name.to_lowercase())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> btree_map::Iter<String, String> {
+        self.entries.iter()
+    }
+
+    // Read the metadata for one kind of resource from response headers
+    pub fn from_headers(headers: &Headers, kind: MetadataKind) -> Metadata {
+        let prefix = kind.prefix().to_lowercase();
+        let mut meta = Metadata::new();
+        for header in headers.iter() {
+            let name = header.name().to_lowercase();
+            if !name.starts_with(&prefix) {
+                continue
+            }
+            if let Some(values) = headers.get_raw(header.name()) {
+                if !values.is_empty() {
+                    meta.insert(&name[prefix.len()..], decode_value(&values[0]));
+                }
+            }
+        }
+        meta
+    }
+
+    pub fn set_headers(&self, headers: &mut Headers, kind: MetadataKind) {
+        for (name, value) in self.iter() {
+            set_metadata_header(headers, kind, name, value);
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Metadata {
+    type Item = (&'a String, &'a String);
+    type IntoIter = btree_map::Iter<'a, String, String>;
+
+    fn into_iter(self) -> btree_map::Iter<'a, String, String> {
+        self.entries.iter()
+    }
+}
+
+impl<K: AsRef<str>, V: Into<String>> FromIterator<(K, V)> for Metadata {
+    fn from_iter<I: IntoIterator<Item=(K, V)>>(iter: I) -> Metadata {
+        let mut meta = Metadata::new();
+        for (name, value) in iter {
+            meta.insert(name, value);
+        }
+        meta
+    }
+}
+
+pub fn set_metadata_header<K: Display, V: AsRef<str>>(
+    headers: &mut Headers, kind: MetadataKind, name: K, value: V
+) {
+    let name = format!("{}{}", kind.prefix(), name);
+    headers.set_raw(name, vec![encode_value(value.as_ref()).into_bytes()]);
+}
+
+/*
+ * For metadata Swift reads itself, such as temp URL keys, which it
+ * uses as is rather than decoding
+ */
+pub fn set_raw_metadata_header<K: Display, V: Into<String>>(
+    headers: &mut Headers, kind: MetadataKind, name: K, value: V
+) {
+    let name = format!("{}{}", kind.prefix(), name);
+    headers.set_raw(name, vec![value.into().into_bytes()]);
+}
+
+// Reads back what set_raw_metadata_header set, without decoding it
+pub fn raw_metadata_header<K: Display>(
+    headers: &Headers, kind: MetadataKind, name: K
+) -> Option<String> {
+    let name = format!("{}{}", kind.prefix(), name);
+    headers.get_raw(&name).and_then(|v| v.get(0)).map(
+        |v| String::from_utf8_lossy(v).into_owned())
+}
+
+pub fn remove_metadata_header<K: Display>(
+    headers: &mut Headers, kind: MetadataKind, name: K
+) {
+    let name = format!("{}{}", kind.remove_prefix(), name);
+    headers.set_raw(name, vec![b"x".to_vec()]);
+}
+
+/*
+ * Header values can only safely carry ASCII, so anything else is
+ * percent-encoded, as the other Swift clients do
+ */
+pub fn encode_value(value: &str) -> String {
+    percent_encode(value.as_bytes(), METADATA_ENCODE_SET).to_string()
+}
+
+/*
+ * Values are only decoded if they're exactly what encode_value would
+ * have produced, so a literal "%41" set by another client is left alone
+ */
+pub fn decode_value(value: &[u8]) -> String {
+    if let Ok(v) = percent_decode(value).decode_utf8() {
+        if encode_value(&v).as_bytes() == value {
+            return v.into_owned()
+        }
+    }
+    String::from_utf8_lossy(value).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::header::Headers;
+
+    use client::metadata::{Metadata, MetadataKind, decode_value, encode_value};
+
+    #[test]
+    fn value_round_trip() {
+        for value in &["plain", "100%41", "%zz", "caf\u{e9}", "tab\there", ""] {
+            assert_eq!(decode_value(encode_value(value).as_bytes()), *value);
+        }
+        assert_eq!(encode_value("100%41"), "100%2541");
+        assert_eq!(encode_value("caf\u{e9}"), "caf%C3%A9");
+    }
+
+    #[test]
+    fn foreign_values_kept() {
+        // Set by clients that don't quote values
+        assert_eq!(decode_value(b"100%41"), "100%41");
+        assert_eq!(decode_value(b"a%20b"), "a%20b");
+        assert_eq!(decode_value("caf\u{e9}".as_bytes()), "caf\u{e9}");
+    }
+
+    #[test]
+    fn headers_round_trip() {
+        let mut meta = Metadata::new();
+        meta.insert("Colour", "100%41 blue");
+        meta.insert("Name", "caf\u{e9}");
+        let mut headers = Headers::new();
+        meta.set_headers(&mut headers, MetadataKind::Object);
+        assert_eq!(headers.get_raw("X-Object-Meta-Colour"),
+                   Some(&[b"100%2541 blue".to_vec()][..]));
+        assert_eq!(Metadata::from_headers(&headers, MetadataKind::Object), meta);
+        assert_eq!(Metadata::from_headers(&headers, MetadataKind::Container),
+                   Metadata::new());
+    }
+}
@@ -1,2 +1,15 @@
 pub mod request;
+pub mod bulk;
+pub mod checksum;
+pub mod download;
 pub mod errors;
+pub mod formpost;
+pub mod info;
+pub mod large_objects;
+pub mod listing;
+pub mod metadata;
+pub mod object;
+pub mod parallel;
+pub mod response;
+pub mod retry;
+pub mod tempurl;
@@ -0,0 +1,329 @@
+use chrono::{DateTime, UTC};
+use hyper::header::Headers;
+use url::percent_encoding::percent_decode;
+
+use client::errors::SwiftError;
+use client::metadata::{Metadata, MetadataKind};
+use client::request::encode_path;
+use client::response::{header_parse, header_string, parse_http_date};
+
+/*
+ * Typed object metadata, from the headers of a HEAD or GET
+ */
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjectMetadata {
+    pub etag: Option<String>,
+    pub content_length: u64,
+    pub content_type: Option<String>,
+    pub last_modified: Option<DateTime<UTC>>,
+    pub metadata: Metadata,
+    // Set when the object is a large object manifest
+    pub manifest: Option<ObjectManifest>
+}
+
+impl ObjectMetadata {
+    pub fn from_headers(headers: &Headers) -> Result<ObjectMetadata, SwiftError> {
+        Ok(ObjectMetadata {
+            etag: header_string(headers, "Etag"),
+            content_length: try!(
+                header_parse(headers, "Content-Length")).unwrap_or(0),
+            content_type: header_string(headers, "Content-Type"),
+            last_modified: header_string(headers, "Last-Modified").and_then(
+                |d| parse_http_date(&d)),
+            metadata: Metadata::from_headers(headers, MetadataKind::Object),
+            manifest: ObjectManifest::from_headers(headers)
+        })
+    }
+}
+
+/*
+ * Large object manifests. The data of a dynamic large object is every
+ * object in the container whose name starts with the prefix.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum ObjectManifest {
+    Dynamic { container: String, prefix: String },
+    Static
+}
+
+impl ObjectManifest {
+    // Works on the headers of both GET and HEAD responses
+    pub fn from_headers(headers: &Headers) -> Option<ObjectManifest> {
+        if let Some(manifest) = headers.get_raw("X-Object-Manifest") {
+            if !manifest.is_empty() {
+                // Unquoted like a path, as Swift does, before it's split
+                let manifest = percent_decode(&manifest[0]).decode_utf8_lossy();
+                let mut parts = manifest.splitn(2, '/');
+                let container = String::from(parts.next().unwrap_or(""));
+                let prefix = String::from(parts.next().unwrap_or(""));
+                return Some(ObjectManifest::Dynamic {
+                    container: container,
+                    prefix: prefix
+                })
+            }
+        }
+        match header_string(headers, "X-Static-Large-Object") {
+            Some(ref s) if s.to_lowercase() == "true" => Some(ObjectManifest::Static),
+            _ => None
+        }
+    }
+
+    // The X-Object-Manifest value for a dynamic large object
+    pub fn dynamic_header_value(container: &str, prefix: &str) -> String {
+        encode_path(&format!("{}/{}", container, prefix))
+    }
+}
+
+/*
+ * The result of a server-side copy
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct CopyObjectResult {
+    pub etag: Option<String>,
+    // The source container and object
+    pub copied_from: Option<String>,
+    pub copied_from_account: Option<String>,
+    pub last_modified: Option<DateTime<UTC>>
+}
+
+impl CopyObjectResult {
+    pub fn from_headers(headers: &Headers) -> CopyObjectResult {
+        CopyObjectResult {
+            etag: header_string(headers, "Etag"),
+            copied_from: header_string(headers, "X-Copied-From"),
+            copied_from_account: header_string(headers, "X-Copied-From-Account"),
+            last_modified: header_string(headers, "Last-Modified").and_then(
+                |d| parse_http_date(&d))
+        }
+    }
+}
+
+/*
+ * Ranged and conditional GETs
+ */
+
+// A Content-Range, e.g. "bytes 0-99/1000"
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ContentRange {
+    pub start: u64,
+    // Inclusive, as in the header
+    pub end: u64,
+    // Unknown if the server sent "*"
+    pub total: Option<u64>
+}
+
+impl ContentRange {
+    pub fn parse(s: &str) -> Option<ContentRange> {
+        let s = s.trim();
+        if !s.starts_with("bytes ") {
+            return None
+        }
+        let mut range_total = s["bytes ".len()..].splitn(2, '/');
+        let mut start_end = range_total.next().unwrap_or("").splitn(2, '-');
+        let start = start_end.next().and_then(|v| v.trim().parse::<u64>().ok());
+        let end = start_end.next().and_then(|v| v.trim().parse::<u64>().ok());
+        let total = range_total.next().and_then(|v| v.trim().parse::<u64>().ok());
+        match (start, end) {
+            (Some(start), Some(end)) if start <= end => Some(ContentRange {
+                start: start,
+                end: end,
+                total: total
+            }),
+            _ => None
+        }
+    }
+
+    // None if the range is backwards, or too long to count
+    pub fn length(&self) -> Option<u64> {
+        self.end.checked_sub(self.start).and_then(|l| l.checked_add(1))
+    }
+}
+
+// One part of a multipart/byteranges response
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjectPart {
+    pub content_type: Option<String>,
+    pub range: ContentRange,
+    pub data: Vec<u8>
+}
+
+// e.g. "multipart/byteranges; boundary=abc"
+pub fn byteranges_boundary(content_type: &str) -> Option<String> {
+    let mut params = content_type.split(';');
+    match params.next() {
+        Some(t) if t.trim().to_lowercase() == "multipart/byteranges" => (),
+        _ => return None
+    };
+    for param in params {
+        let mut kv = param.splitn(2, '=');
+        if kv.next().map(|k| k.trim().to_lowercase()) == Some(String::from("boundary")) {
+            return kv.next().map(|v| String::from(v.trim().trim_matches('"')))
+        }
+    }
+    None
+}
+
+/*
+ * The parts are read using the length from their Content-Range rather
+ * than by searching for the next boundary, so binary data can't be
+ * mistaken for one
+ */
+pub fn parse_byteranges(body: &[u8], boundary: &str)
+    -> Result<Vec<ObjectPart>, SwiftError>
+{
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let mut parts = Vec::new();
+    let mut pos = match find_bytes(body, &delimiter, 0) {
+        Some(p) => p + delimiter.len(),
+        None => return Err(SwiftError::Parse(String::from(
+            "No boundary in multipart/byteranges body")))
+    };
+    loop {
+        // The last boundary is followed by "--"
+        if body[pos..].starts_with(b"--") {
+            return Ok(parts)
+        }
+        let headers_end = match find_bytes(body, b"\r\n\r\n", pos) {
+            Some(p) => p,
+            None => return Err(SwiftError::Parse(String::from(
+                "Unterminated part headers in multipart/byteranges body")))
+        };
+        let mut content_type = None;
+        let mut range = None;
+        for line in String::from_utf8_lossy(&body[pos..headers_end]).split("\r\n") {
+            let mut kv = line.splitn(2, ':');
+            let name = kv.next().unwrap_or("").trim().to_lowercase();
+            let value = kv.next().unwrap_or("").trim();
+            if name == "content-type" {
+                content_type = Some(String::from(value));
+            } else if name == "content-range" {
+                range = ContentRange::parse(value);
+            }
+        }
+        let range = match range {
+            Some(r) => r,
+            None => return Err(SwiftError::Parse(String::from(
+                "Part without a Content-Range in multipart/byteranges body")))
+        };
+        let length = match range.length() {
+            Some(l) => l,
+            None => return Err(SwiftError::Parse(format!(
+                "Invalid range {}-{} in multipart/byteranges body",
+                range.start, range.end)))
+        };
+        let start = headers_end + 4;
+        if length > (body.len() - start) as u64 {
+            return Err(SwiftError::Parse(String::from(
+                "Truncated part in multipart/byteranges body")))
+        }
+        let end = start + length as usize;
+        parts.push(ObjectPart {
+            content_type: content_type,
+            range: range,
+            data: body[start..end].to_vec()
+        });
+        pos = match find_bytes(body, &delimiter, end) {
+            Some(p) => p + delimiter.len(),
+            None => return Err(SwiftError::Parse(String::from(
+                "Unterminated multipart/byteranges body")))
+        };
+    }
+}
+
+fn find_bytes(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    if from > haystack.len() {
+        return None
+    }
+    haystack[from..].windows(needle.len()).position(
+        |w| w == needle).map(|p| p + from)
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::header::Headers;
+
+    use client::errors::SwiftError;
+    use client::object::{ContentRange, ObjectManifest, ObjectPart, parse_byteranges};
+
+    fn dynamic_manifest(value: &str) -> Option<ObjectManifest> {
+        let mut headers = Headers::new();
+        headers.set_raw("X-Object-Manifest", vec![value.as_bytes().to_vec()]);
+        ObjectManifest::from_headers(&headers)
+    }
+
+    #[test]
+    fn dynamic_manifest_round_trip() {
+        for &(container, prefix) in &[("segments", "big/dlo/1/"),
+                                      ("my c", "pre fix?#`/"),
+                                      ("100%", "%41/caf\u{e9}")] {
+            let value = ObjectManifest::dynamic_header_value(container, prefix);
+            assert_eq!(dynamic_manifest(&value), Some(ObjectManifest::Dynamic {
+                container: String::from(container),
+                prefix: String::from(prefix)
+            }));
+        }
+    }
+
+    #[test]
+    fn dynamic_manifest_unquoted() {
+        assert_eq!(dynamic_manifest("my%20c/a%2Fb"), Some(ObjectManifest::Dynamic {
+            container: String::from("my c"),
+            prefix: String::from("a/b")
+        }));
+    }
+
+    fn part_body(ranges: &[(&str, &str)]) -> Vec<u8> {
+        let mut body = String::new();
+        for &(range, data) in ranges {
+            body.push_str(&format!(
+                "--abc\r\nContent-Type: text/plain\r\nContent-Range: {}\r\n\r\n{}\r\n",
+                range, data));
+        }
+        body.push_str("--abc--\r\n");
+        body.into_bytes()
+    }
+
+    fn is_parse_error(result: Result<Vec<ObjectPart>, SwiftError>) -> bool {
+        match result {
+            Err(SwiftError::Parse(_)) => true,
+            _ => false
+        }
+    }
+
+    #[test]
+    fn content_range() {
+        assert_eq!(ContentRange::parse("bytes 2-5/10"), Some(ContentRange {
+            start: 2,
+            end: 5,
+            total: Some(10)
+        }));
+        assert_eq!(ContentRange::parse("bytes 5-2/10"), None);
+        assert_eq!(ContentRange::parse("bytes 2-5/10").and_then(|r| r.length()), Some(4));
+        let huge = ContentRange::parse("bytes 0-18446744073709551615/*").unwrap();
+        assert_eq!(huge.length(), None);
+        let backwards = ContentRange { start: 5, end: 2, total: None };
+        assert_eq!(backwards.length(), None);
+    }
+
+    #[test]
+    fn byteranges() {
+        let body = part_body(&[("bytes 0-2/10", "abc"), ("bytes 7-9/10", "h\r\n")]);
+        let parts = parse_byteranges(&body, "abc").unwrap();
+        assert_eq!(parts.iter().map(|p| p.data.clone()).collect::<Vec<Vec<u8>>>(),
+                   vec![b"abc".to_vec(), b"h\r\n".to_vec()]);
+        assert_eq!(parts[1].range.start, 7);
+        assert_eq!(parts[0].content_type, Some(String::from("text/plain")));
+    }
+
+    #[test]
+    fn byteranges_invalid_ranges() {
+        let body = part_body(&[("bytes 0-18446744073709551615/*", "abc")]);
+        assert!(is_parse_error(parse_byteranges(&body, "abc")));
+        // Longer than the rest of the body
+        let body = part_body(&[("bytes 0-99/100", "abc")]);
+        assert!(is_parse_error(parse_byteranges(&body, "abc")));
+        let body = part_body(&[("bytes 5-2/10", "abc")]);
+        assert!(is_parse_error(parse_byteranges(&body, "abc")));
+    }
+}
@@ -0,0 +1,206 @@
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, sync_channel};
+use std::thread;
+
+use client::errors::SwiftError;
+
+/*
+ * A bounded pool of worker threads for segmented transfers
+ */
+
+/*
+ * Run work on each job from jobs, using up to concurrency threads, and
+ * return the results in the same order as the jobs.
+ *
+ * The jobs are only pulled from the iterator as the workers are ready
+ * for them, so no more than concurrency + 1 jobs (one being made, and
+ * one per worker) are held at once. The first error, from either the
+ * jobs or the work, stops any more jobs from being started and is
+ * returned.
+ */
+pub fn map_ordered<I, T, U, F>(jobs: I, concurrency: usize, work: F)
+    -> Result<Vec<U>, SwiftError>
+    where I: Iterator<Item=Result<T, SwiftError>>,
+          T: Send + 'static,
+          U: Send + 'static,
+          F: Fn(usize, T) -> Result<U, SwiftError> + Send + Sync + 'static
+{
+    if concurrency <= 1 {
+        let mut results = Vec::new();
+        for (index, job) in jobs.enumerate() {
+            results.push(try!(work(index, try!(job))));
+        }
+        return Ok(results)
+    }
+
+    // Each job is handed straight to a worker, rather than queued
+    let (job_tx, job_rx) = sync_channel::<(usize, T)>(0);
+    let job_rx = Arc::new(Mutex::new(job_rx));
+    let (result_tx, result_rx) = channel();
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let work = Arc::new(work);
+
+    let mut workers = Vec::new();
+    for _ in 0..concurrency {
+        let job_rx = job_rx.clone();
+        let result_tx = result_tx.clone();
+        let cancelled = cancelled.clone();
+        let work = work.clone();
+        workers.push(thread::spawn(move || {
+            loop {
+                // The lock is only held while waiting for the next job
+                let job = match job_rx.lock() {
+                    Ok(rx) => rx.recv(),
+                    Err(_) => return
+                };
+                let (index, item) = match job {
+                    Ok(j) => j,
+                    Err(_) => return
+                };
+                // Keep draining the queue so that the sender isn't blocked
+                if cancelled.load(Ordering::SeqCst) {
+                    continue
+                }
+                let result = work(index, item);
+                if result.is_err() {
+                    cancelled.store(true, Ordering::SeqCst);
+                }
+                if result_tx.send((index, result)).is_err() {
+                    return
+                }
+            }
+        }));
+    }
+    // Only the workers hold the receiver, so sending fails rather than
+    // blocking forever if they have all gone
+    drop(job_rx);
+    drop(result_tx);
+
+    let mut first_err = None;
+    let mut sent = 0;
+    for (index, job) in jobs.enumerate() {
+        if cancelled.load(Ordering::SeqCst) {
+            break
+        }
+        match job {
+            Ok(item) => {
+                if job_tx.send((index, item)).is_err() {
+                    break
+                }
+                sent += 1;
+            },
+            Err(e) => {
+                cancelled.store(true, Ordering::SeqCst);
+                first_err = Some(e);
+                break
+            }
+        };
+    }
+    drop(job_tx);
+
+    let mut results = Vec::new();
+    for (index, result) in result_rx.iter() {
+        match result {
+            Ok(u) => results.push((index, u)),
+            Err(e) => {
+                if first_err.is_none() {
+                    first_err = Some(e);
+                }
+            }
+        };
+    }
+    for worker in workers {
+        let _ = worker.join();
+    }
+    if let Some(e) = first_err {
+        return Err(e)
+    }
+    // Only possible if a worker panicked
+    if results.len() != sent {
+        let err = io::Error::new(io::ErrorKind::Other, "Worker thread failed");
+        return Err(SwiftError::Io(err))
+    }
+    results.sort_by_key(|&(index, _)| index);
+    Ok(results.into_iter().map(|(_, u)| u).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    use client::errors::SwiftError;
+    use client::parallel::map_ordered;
+
+    fn ok_jobs(count: usize) -> Vec<Result<usize, SwiftError>> {
+        (0..count).map(Ok).collect()
+    }
+
+    fn error_message(result: Result<Vec<usize>, SwiftError>) -> String {
+        match result {
+            Err(SwiftError::Parse(msg)) => msg,
+            Err(e) => panic!("Unexpected error: {}", e),
+            Ok(r) => panic!("Expected an error, got {:?}", r)
+        }
+    }
+
+    #[test]
+    fn results_in_job_order() {
+        for concurrency in &[1, 4] {
+            // Later jobs finish first
+            let results = map_ordered(ok_jobs(20).into_iter(), *concurrency, |index, job| {
+                thread::sleep(Duration::from_millis(20 - index as u64));
+                Ok(job * 2)
+            }).unwrap();
+            assert_eq!(results, (0..20).map(|j| j * 2).collect::<Vec<usize>>());
+        }
+    }
+
+    #[test]
+    fn job_error_returned() {
+        let mut jobs = ok_jobs(20);
+        jobs[5] = Err(SwiftError::Parse(String::from("job 5")));
+        let result = map_ordered(jobs.into_iter(), 4, |_, job| Ok(job));
+        assert_eq!(error_message(result), "job 5");
+    }
+
+    #[test]
+    fn work_error_returned() {
+        let result = map_ordered(ok_jobs(20).into_iter(), 4, |index, job| {
+            if index == 5 {
+                return Err(SwiftError::Parse(String::from("work 5")))
+            }
+            Ok(job)
+        });
+        assert_eq!(error_message(result), "work 5");
+    }
+
+    #[test]
+    fn no_jobs_started_after_error() {
+        let pulled = Arc::new(AtomicUsize::new(0));
+        let started = Arc::new(AtomicUsize::new(0));
+        let jobs = {
+            let pulled = pulled.clone();
+            ok_jobs(100).into_iter().inspect(move |_| {
+                pulled.fetch_add(1, Ordering::SeqCst);
+            })
+        };
+        let work_started = started.clone();
+        let result = map_ordered(jobs, 2, move |index, job| {
+            work_started.fetch_add(1, Ordering::SeqCst);
+            if index == 0 {
+                return Err(SwiftError::Parse(String::from("work 0")))
+            }
+            thread::sleep(Duration::from_millis(50));
+            Ok(job)
+        });
+        assert_eq!(error_message(result), "work 0");
+        // At most the jobs already handed out when the first one failed
+        assert!(pulled.load(Ordering::SeqCst) <= 4);
+        assert!(started.load(Ordering::SeqCst) <= 3);
+    }
+}
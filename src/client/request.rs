@@ -1,15 +1,49 @@
-use hyper::header::Headers;
+use chrono::{DateTime, UTC};
+use hyper::header::{ByteRangeSpec, ContentLength, Header, Headers, Range};
 use hyper::method::Method;
-use reqwest::{Body, RequestBuilder, Response};
+use reqwest::{Body, RequestBuilder, Response, StatusCode};
 
+use std::cmp;
+use std::collections::VecDeque;
 use std::fmt::Display;
+use std::io::{self, Read, Write};
+use std::path::Path;
 use std::sync::Arc;
 use std::vec::Vec;
+use url::form_urlencoded;
+use url::percent_encoding::{
+    DEFAULT_ENCODE_SET, PATH_SEGMENT_ENCODE_SET, utf8_percent_encode
+};
 
-//use auth::errors::AuthError;
-use auth::sessions::Auth;
+use auth::errors::AuthError;
+use auth::sessions::{Auth, SessionInfo};
+use client::bulk::{
+    BulkDeleteResult, DEFAULT_MAX_DELETES_PER_REQUEST, parse_bulk_delete
+};
+use client::checksum::{
+    Md5Digest, Md5Reader, check_md5, is_manifest_etag, md5_hex
+};
+use client::download::ObjectDownload;
 use client::errors::SwiftError;
+use client::formpost::FormPost;
+use client::info::{Capabilities, GetInfo};
+use client::large_objects::{DloUpload, SloUpload};
+use client::listing::{
+    AccountListing, AccountStats, ContainerEntry, ContainerListing,
+    ContainerStats, ObjectEntry, parse_account_listing, parse_container_listing
+};
+use client::metadata::{
+    Metadata, MetadataKind, remove_metadata_header, set_metadata_header,
+    set_raw_metadata_header
+};
+use client::object::{
+    ContentRange, CopyObjectResult, ObjectMetadata, ObjectPart,
+    byteranges_boundary, parse_byteranges
+};
+use client::response::{check_status, header_string};
+use client::tempurl::{TEMP_URL_KEY, TEMP_URL_KEY_2, TempUrl};
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Format {
     JSON,
     XML,
@@ -30,6 +64,21 @@ impl<AS: Sized+Auth> SwiftConnection<AS> {
         }
     }
 
+    /*
+     * The storage URL, region, token expiry and catalogue the auth
+     * session is currently using
+     */
+    pub fn session_info(&self) -> Result<SessionInfo, SwiftError> {
+        self.auth.session_info().map_err(SwiftError::Auth)
+    }
+
+    /*
+     * The cluster's capabilities and limits, from /info
+     */
+    pub fn get_info(&self) -> GetInfo<AS> {
+        GetInfo::new(self.auth.clone())
+    }
+
     pub fn head_account(&self) -> HeadAccount<AS> {
         HeadAccount::new(self.auth.clone())
     }
@@ -48,6 +97,44 @@ impl<AS: Sized+Auth> SwiftConnection<AS> {
         GetContainer::new(self.auth.clone(), container)
     }
 
+    pub fn put_container(
+        &self, container: String
+    ) -> PutContainer<AS> {
+        PutContainer::new(self.auth.clone(), container)
+    }
+
+    pub fn head_container(
+        &self, container: String
+    ) -> HeadContainer<AS> {
+        HeadContainer::new(self.auth.clone(), container)
+    }
+
+    pub fn post_container(
+        &self, container: String
+    ) -> PostContainer<AS> {
+        PostContainer::new(self.auth.clone(), container)
+    }
+
+    pub fn delete_container(
+        &self, container: String
+    ) -> DeleteContainer<AS> {
+        DeleteContainer::new(self.auth.clone(), container)
+    }
+
+    /*
+     * Iterate over all of the containers in the account, or all of the
+     * objects in a container, fetching pages as required
+     */
+    pub fn iter_account(&self) -> AccountIterator<AS> {
+        AccountIterator::new(self.auth.clone())
+    }
+
+    pub fn iter_container(
+        &self, container: String
+    ) -> ContainerIterator<AS> {
+        ContainerIterator::new(self.auth.clone(), container)
+    }
+
     pub fn get_object(
         &self, container: String, object: String
     ) -> GetObject<AS> {
@@ -59,6 +146,117 @@ impl<AS: Sized+Auth> SwiftConnection<AS> {
     ) -> PutObject<AS, T> {
         PutObject::new(self.auth.clone(), container, object, body)
     }
+
+    /*
+     * Upload from a reader, checking the MD5 of the data sent against
+     * the etag that Swift returns
+     */
+    pub fn put_object_stream<R: Read+Send+'static>(
+        &self, container: String, object: String, source: R
+    ) -> PutObject<AS, Body> {
+        let reader = Md5Reader::new(source);
+        let digest = reader.digest();
+        let mut put = PutObject::new(
+            self.auth.clone(), container, object, Body::new(reader));
+        put.checksum = Some(BodyChecksum::Streamed(digest));
+        put
+    }
+
+    pub fn head_object(
+        &self, container: String, object: String
+    ) -> HeadObject<AS> {
+        HeadObject::new(self.auth.clone(), container, object)
+    }
+
+    pub fn post_object(
+        &self, container: String, object: String
+    ) -> PostObject<AS> {
+        PostObject::new(self.auth.clone(), container, object)
+    }
+
+    pub fn delete_object(
+        &self, container: String, object: String
+    ) -> DeleteObject<AS> {
+        DeleteObject::new(self.auth.clone(), container, object)
+    }
+
+    /*
+     * Delete any number of objects and empty containers, given as
+     * "container/object" or "container", using the bulk middleware
+     */
+    pub fn bulk_delete<I: IntoIterator<Item=String>>(
+        &self, paths: I
+    ) -> BulkDelete<AS, I::IntoIter> {
+        BulkDelete::new(self.auth.clone(), paths.into_iter())
+    }
+
+    /*
+     * Copy an object on the server, without downloading it
+     */
+    pub fn copy_object(
+        &self, container: String, object: String,
+        destination_container: String, destination_object: String
+    ) -> CopyObject<AS> {
+        CopyObject::new(
+            self.auth.clone(), container, object,
+            destination_container, destination_object)
+    }
+
+    /*
+     * Generate a temporary URL for an object, signed with the account
+     * or container temp URL key
+     */
+    pub fn temp_url(
+        &self, container: String, object: String, key: String
+    ) -> TempUrl<AS> {
+        TempUrl::new(self.auth.clone(), container, object, key)
+    }
+
+    pub fn temp_url_prefix(
+        &self, container: String, prefix: String, key: String
+    ) -> TempUrl<AS> {
+        TempUrl::new_prefix(self.auth.clone(), container, prefix, key)
+    }
+
+    /*
+     * Sign a form for uploading files from a browser, named with the
+     * prefix in the container
+     */
+    pub fn form_post(
+        &self, container: String, prefix: String
+    ) -> FormPost<AS> {
+        FormPost::new(self.auth.clone(), container, prefix)
+    }
+
+    /*
+     * Download an object into a file, fetching byte ranges in parallel
+     */
+    pub fn download_object<P: AsRef<Path>>(
+        &self, container: String, object: String, path: P
+    ) -> ObjectDownload<AS> {
+        ObjectDownload::new(
+            self.auth.clone(), container, object, path.as_ref().to_path_buf())
+    }
+
+    /*
+     * Upload an object of any size as a static large object, reading
+     * the segments from source
+     */
+    pub fn slo_upload<R: Read>(
+        &self, container: String, object: String, source: R
+    ) -> SloUpload<AS, R> {
+        SloUpload::new(self.auth.clone(), container, object, source)
+    }
+
+    /*
+     * Upload an object as a dynamic large object, with the segments
+     * under a common prefix
+     */
+    pub fn dlo_upload<R: Read>(
+        &self, container: String, object: String, source: R
+    ) -> DloUpload<AS, R> {
+        DloUpload::new(self.auth.clone(), container, object, source)
+    }
 }
 
 pub trait RunSwiftRequest {
@@ -68,7 +266,7 @@ pub trait RunSwiftRequest {
     fn add_query_param<K: Display, V: Display>(
         &self, name: &K, value: &V, query_params: &mut Vec<String>
     ) -> () {
-        query_params.push(format!("{}={}", name, value));
+        query_params.push(format!("{}={}", name, encode_query_value(value)));
     }
 
     fn add_optional_query_param<K: Display, V: Display>(
@@ -77,12 +275,47 @@ pub trait RunSwiftRequest {
     ) -> () {
         match value {
             &Some(ref v) => query_params.push(
-                format!("{}={}", name, v)),
+                format!("{}={}", name, encode_query_value(v))),
             &None => ()
         }
     }
 }
 
+/*
+ * Setters for the headers sent with any request
+ */
+pub trait SwiftRequestHeaders: Sized {
+    fn headers_mut(&mut self) -> &mut Headers;
+
+    fn header<H: Header>(mut self, header: H) -> Self {
+        self.headers_mut().set(header);
+        self
+    }
+
+    fn raw_header<K: Into<String>, V: Into<String>>(
+        mut self, name: K, value: V
+    ) -> Self {
+        let value: String = value.into();
+        self.headers_mut().set_raw(name.into(), vec![value.into_bytes()]);
+        self
+    }
+
+    fn headers(mut self, headers: Headers) -> Self {
+        self.headers_mut().extend(headers.iter());
+        self
+    }
+}
+
+/*
+ * Requests that can parse their response into a typed result
+ */
+pub trait RunTypedSwiftRequest {
+    type Output;
+
+    fn run_typed_request(self)
+        -> Result<Self::Output, SwiftError>;
+}
+
 /*
  * Get Account
  */
@@ -90,7 +323,9 @@ pub struct GetAccount<A> {
     marker: Option<String>,
     limit: u32,
     prefix: Option<String>,
+    delimiter: Option<String>,
     end_marker: Option<String>,
+    reverse: bool,
     format: Format,
     headers: Headers,
     auth: Arc<A>
@@ -102,12 +337,56 @@ impl<AS: Sized+Auth> GetAccount<AS> {
             marker: None,
             limit: 10000,
             prefix: None,
+            delimiter: None,
             end_marker: None,
+            reverse: false,
             format: Format::JSON,
             headers: Headers::new(),
             auth: auth
         }
     }
+
+    pub fn marker<S: Into<String>>(mut self, marker: S) -> GetAccount<AS> {
+        self.marker = Some(marker.into());
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> GetAccount<AS> {
+        self.limit = limit;
+        self
+    }
+
+    pub fn prefix<S: Into<String>>(mut self, prefix: S) -> GetAccount<AS> {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    pub fn delimiter<S: Into<String>>(mut self, delimiter: S) -> GetAccount<AS> {
+        self.delimiter = Some(delimiter.into());
+        self
+    }
+
+    pub fn end_marker<S: Into<String>>(mut self, end_marker: S) -> GetAccount<AS> {
+        self.end_marker = Some(end_marker.into());
+        self
+    }
+
+    // List in reverse order
+    pub fn reverse(mut self, reverse: bool) -> GetAccount<AS> {
+        self.reverse = reverse;
+        self
+    }
+
+    pub fn format(mut self, format: Format) -> GetAccount<AS> {
+        self.format = format;
+        self
+    }
+}
+
+impl<AS: Sized+Auth> SwiftRequestHeaders for GetAccount<AS> {
+    fn headers_mut(&mut self) -> &mut Headers {
+        &mut self.headers
+    }
 }
 
 impl<AS: Sized+Auth> RunSwiftRequest for GetAccount<AS> {
@@ -119,8 +398,13 @@ impl<AS: Sized+Auth> RunSwiftRequest for GetAccount<AS> {
             &"marker", &self.marker, &mut query_params);
         self.add_optional_query_param(
             &"prefix", &self.prefix, &mut query_params);
+        self.add_optional_query_param(
+            &"delimiter", &self.delimiter, &mut query_params);
         self.add_optional_query_param(
             &"end_marker", &self.end_marker, &mut query_params);
+        if self.reverse {
+            self.add_query_param(&"reverse", &"true", &mut query_params);
+        };
         match self.format {
             Format::JSON => self.add_query_param(
                 &"format", &"json", &mut query_params),
@@ -130,12 +414,21 @@ impl<AS: Sized+Auth> RunSwiftRequest for GetAccount<AS> {
         };
         let path = "?".to_string() + &query_params.join("&").to_string();
 
-        match build_request(
+        send_request(
             self.auth.as_ref(), Method::Get, path, self.headers.clone()
-        ) {
-            Ok(req) => make_request(req),
-            Err(e) => Err(e)
-        }
+        )
+    }
+}
+
+impl<AS: Sized+Auth> RunTypedSwiftRequest for GetAccount<AS> {
+    type Output = AccountListing;
+
+    fn run_typed_request(self)
+            -> Result<AccountListing, SwiftError> {
+        let format = self.format;
+        let delimiter = self.delimiter.clone();
+        let resp = try!(check_status(try!(self.run_request())));
+        parse_account_listing(resp, format, &delimiter)
     }
 }
 
@@ -156,17 +449,29 @@ impl<AS: Sized+Auth> HeadAccount<AS> {
     }
 }
 
+impl<AS: Sized+Auth> SwiftRequestHeaders for HeadAccount<AS> {
+    fn headers_mut(&mut self) -> &mut Headers {
+        &mut self.headers
+    }
+}
+
 impl<AS: Sized+Auth> RunSwiftRequest for HeadAccount<AS> {
     fn run_request(self)
             -> Result<Response, SwiftError> {
         let path = "".to_string();
-        match build_request(
-                self.auth.as_ref(),
-                Method::Head,
-                path, self.headers.clone()) {
-            Ok(req) => make_request(req),
-            Err(e) => Err(e)
-        }
+        send_request(
+            self.auth.as_ref(), Method::Head, path, self.headers.clone()
+        )
+    }
+}
+
+impl<AS: Sized+Auth> RunTypedSwiftRequest for HeadAccount<AS> {
+    type Output = AccountStats;
+
+    fn run_typed_request(self)
+            -> Result<AccountStats, SwiftError> {
+        let resp = try!(check_status(try!(self.run_request())));
+        AccountStats::from_headers(resp.headers())
     }
 }
 
@@ -185,19 +490,60 @@ impl<AS: Sized+Auth> PostAccount<AS> {
             auth: auth
         }
     }
+
+    pub fn metadata<K: Display, V: AsRef<str>>(
+        mut self, name: K, value: V
+    ) -> PostAccount<AS> {
+        set_metadata_header(&mut self.headers, MetadataKind::Account, name, value);
+        self
+    }
+
+    pub fn metadata_map(mut self, metadata: &Metadata) -> PostAccount<AS> {
+        metadata.set_headers(&mut self.headers, MetadataKind::Account);
+        self
+    }
+
+    pub fn remove_metadata<K: Display>(mut self, name: K) -> PostAccount<AS> {
+        remove_metadata_header(&mut self.headers, MetadataKind::Account, name);
+        self
+    }
+
+    // The key for signing temp URLs for any container in the account
+    pub fn temp_url_key<S: Into<String>>(mut self, key: S) -> PostAccount<AS> {
+        set_raw_metadata_header(&mut self.headers, MetadataKind::Account, TEMP_URL_KEY, key);
+        self
+    }
+
+    // A second key, so that keys can be rotated
+    pub fn temp_url_key_2<S: Into<String>>(mut self, key: S) -> PostAccount<AS> {
+        set_raw_metadata_header(&mut self.headers, MetadataKind::Account, TEMP_URL_KEY_2, key);
+        self
+    }
+}
+
+impl<AS: Sized+Auth> SwiftRequestHeaders for PostAccount<AS> {
+    fn headers_mut(&mut self) -> &mut Headers {
+        &mut self.headers
+    }
 }
 
 impl<AS: Sized+Auth> RunSwiftRequest for PostAccount<AS> {
     fn run_request(self)
             -> Result<Response, SwiftError> {
         let path = "".to_string();
-        match build_request(
-                self.auth.as_ref(),
-                Method::Post,
-                path, self.headers.clone()) {
-            Ok(req) => make_request(req),
-            Err(e) => Err(e)
-        }
+        send_request(
+            self.auth.as_ref(), Method::Post, path, self.headers.clone()
+        )
+    }
+}
+
+impl<AS: Sized+Auth> RunTypedSwiftRequest for PostAccount<AS> {
+    type Output = ();
+
+    fn run_typed_request(self)
+            -> Result<(), SwiftError> {
+        try!(check_status(try!(self.run_request())));
+        Ok(())
     }
 }
 
@@ -212,6 +558,7 @@ pub struct GetContainer<A> {
     delimiter: Option<String>,
     end_marker: Option<String>,
     path: Option<String>,
+    reverse: bool,
     format: Format,
     headers: Headers,
     auth: Arc<A>
@@ -227,11 +574,60 @@ impl<AS: Sized+Auth> GetContainer<AS> {
             delimiter: None,
             end_marker: None,
             path: None,
+            reverse: false,
             format: Format::JSON,
             headers: Headers::new(),
             auth: auth
         }
     }
+
+    pub fn marker<S: Into<String>>(mut self, marker: S) -> GetContainer<AS> {
+        self.marker = Some(marker.into());
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> GetContainer<AS> {
+        self.limit = limit;
+        self
+    }
+
+    pub fn prefix<S: Into<String>>(mut self, prefix: S) -> GetContainer<AS> {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    pub fn delimiter<S: Into<String>>(mut self, delimiter: S) -> GetContainer<AS> {
+        self.delimiter = Some(delimiter.into());
+        self
+    }
+
+    pub fn end_marker<S: Into<String>>(mut self, end_marker: S) -> GetContainer<AS> {
+        self.end_marker = Some(end_marker.into());
+        self
+    }
+
+    // List the objects nested under a pseudo-directory path
+    pub fn path<S: Into<String>>(mut self, path: S) -> GetContainer<AS> {
+        self.path = Some(path.into());
+        self
+    }
+
+    // List in reverse order
+    pub fn reverse(mut self, reverse: bool) -> GetContainer<AS> {
+        self.reverse = reverse;
+        self
+    }
+
+    pub fn format(mut self, format: Format) -> GetContainer<AS> {
+        self.format = format;
+        self
+    }
+}
+
+impl<AS: Sized+Auth> SwiftRequestHeaders for GetContainer<AS> {
+    fn headers_mut(&mut self) -> &mut Headers {
+        &mut self.headers
+    }
 }
 
 impl<AS: Sized+Auth> RunSwiftRequest for GetContainer<AS> {
@@ -249,6 +645,9 @@ impl<AS: Sized+Auth> RunSwiftRequest for GetContainer<AS> {
             &"end_marker", &self.end_marker, &mut query_params);
         self.add_optional_query_param(
             &"path", &self.path, &mut query_params);
+        if self.reverse {
+            self.add_query_param(&"reverse", &"true", &mut query_params);
+        };
         match self.format {
             Format::JSON => self.add_query_param(
                 &"format", &"json", &mut query_params),
@@ -256,129 +655,1453 @@ impl<AS: Sized+Auth> RunSwiftRequest for GetContainer<AS> {
                 &"format", &"xml", &mut query_params),
             Format::Plain => ()
         };
-        let mut path = format!("/{}?", self.container);
+        let mut path = container_path(&self.container) + "?";
         path = path + &query_params.join("&").to_string();
 
-        match build_request(
+        send_request(
             self.auth.as_ref(), Method::Get, path, self.headers.clone()
-        ) {
-            Ok(req) => make_request(req),
-            Err(e) => Err(e)
-        }
+        )
+    }
+}
+
+impl<AS: Sized+Auth> RunTypedSwiftRequest for GetContainer<AS> {
+    type Output = ContainerListing;
+
+    fn run_typed_request(self)
+            -> Result<ContainerListing, SwiftError> {
+        let format = self.format;
+        let delimiter = self.delimiter.clone();
+        let resp = try!(check_status(try!(self.run_request())));
+        parse_container_listing(resp, format, &delimiter)
     }
 }
 
 /*
- * Get Object
+ * Put Container
  */
-pub struct GetObject<A> {
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PutContainerResult {
+    Created,
+    // The container already existed, and its metadata was updated
+    Accepted
+}
+
+pub struct PutContainer<A> {
     container: String,
-    object: String,
-    multipart_manifest_get: bool,
     headers: Headers,
     auth: Arc<A>
 }
 
-impl<AS: Sized+Auth> GetObject<AS> {
-    pub fn new(
-        auth: Arc<AS>, container: String, object: String
-    ) -> GetObject<AS> {
-        GetObject {
+impl<AS: Sized+Auth> PutContainer<AS> {
+    pub fn new(auth: Arc<AS>, container: String) -> PutContainer<AS> {
+        PutContainer {
             container: container,
-            object: object,
-            multipart_manifest_get: false,
             headers: Headers::new(),
             auth: auth
         }
     }
+
+    // Can only be set when the container is created
+    pub fn storage_policy<S: Into<String>>(self, policy: S) -> PutContainer<AS> {
+        self.raw_header("X-Storage-Policy", policy)
+    }
+
+    pub fn metadata<K: Display, V: AsRef<str>>(
+        mut self, name: K, value: V
+    ) -> PutContainer<AS> {
+        set_metadata_header(&mut self.headers, MetadataKind::Container, name, value);
+        self
+    }
+
+    pub fn metadata_map(mut self, metadata: &Metadata) -> PutContainer<AS> {
+        metadata.set_headers(&mut self.headers, MetadataKind::Container);
+        self
+    }
+
+    pub fn read_acl<S: Into<String>>(self, acl: S) -> PutContainer<AS> {
+        self.raw_header("X-Container-Read", acl)
+    }
+
+    pub fn write_acl<S: Into<String>>(self, acl: S) -> PutContainer<AS> {
+        self.raw_header("X-Container-Write", acl)
+    }
 }
 
-impl<AS: Sized+Auth> RunSwiftRequest for GetObject<AS> {
+impl<AS: Sized+Auth> SwiftRequestHeaders for PutContainer<AS> {
+    fn headers_mut(&mut self) -> &mut Headers {
+        &mut self.headers
+    }
+}
+
+impl<AS: Sized+Auth> RunSwiftRequest for PutContainer<AS> {
     fn run_request(self)
-        -> Result<Response, SwiftError>
-    {
-        let mut path = format!("/{}/{}", self.container, self.object);
-        if self.multipart_manifest_get {
-            path = path + &format!("?{}={}", &"multipart-manifest", &"get");
-        };
+            -> Result<Response, SwiftError> {
+        let path = container_path(&self.container);
+        send_request(
+            self.auth.as_ref(), Method::Put, path, self.headers.clone()
+        )
+    }
+}
+
+impl<AS: Sized+Auth> RunTypedSwiftRequest for PutContainer<AS> {
+    type Output = PutContainerResult;
 
-        match build_request(
-                self.auth.as_ref(), Method::Get, path, self.headers.clone()
-        ) {
-            Ok(req) => make_request(req),
-            Err(e) => Err(e)
+    fn run_typed_request(self)
+            -> Result<PutContainerResult, SwiftError> {
+        let resp = try!(check_status(try!(self.run_request())));
+        match *resp.status() {
+            StatusCode::Accepted => Ok(PutContainerResult::Accepted),
+            _ => Ok(PutContainerResult::Created)
         }
     }
 }
 
 /*
- * Put Object
+ * Head Container
  */
-pub struct PutObject<A, T: Into<Body>> {
+pub struct HeadContainer<A> {
     container: String,
-    object: String,
-    multipart_manifest_put: bool,
     headers: Headers,
-    body: T,
     auth: Arc<A>
 }
 
-impl<AS: Sized+Auth, T: Into<Body>> PutObject<AS, T> {
-    pub fn new(
-        auth: Arc<AS>, container: String, object: String, body: T
-    ) -> PutObject<AS, T> {
-        PutObject {
+impl<AS: Sized+Auth> HeadContainer<AS> {
+    pub fn new(auth: Arc<AS>, container: String) -> HeadContainer<AS> {
+        HeadContainer {
             container: container,
-            object: object,
-            multipart_manifest_put: false,
             headers: Headers::new(),
-            body: body,
             auth: auth
         }
     }
 }
 
-impl<AS: Sized+Auth, T: Into<Body>> RunSwiftRequest for PutObject<AS, T> {
+impl<AS: Sized+Auth> SwiftRequestHeaders for HeadContainer<AS> {
+    fn headers_mut(&mut self) -> &mut Headers {
+        &mut self.headers
+    }
+}
+
+impl<AS: Sized+Auth> RunSwiftRequest for HeadContainer<AS> {
     fn run_request(self)
             -> Result<Response, SwiftError> {
-        let mut path = format!("{}/{}", self.container, self.object);
-        if self.multipart_manifest_put {
-            path = path + &format!("?{}={}", &"multipart-manifest", &"put");
-        };
+        let path = container_path(&self.container);
+        send_request(
+            self.auth.as_ref(), Method::Head, path, self.headers.clone()
+        )
+    }
+}
 
-        let put_req = match build_request(
-            self.auth.as_ref(), Method::Put, path, self.headers.clone()
-        ) {
-            Ok(req) => req,
-            Err(e) => return Err(e)
-        };
-        let put_req = put_req.body(self.body);
+impl<AS: Sized+Auth> RunTypedSwiftRequest for HeadContainer<AS> {
+    type Output = ContainerStats;
 
-        make_request(put_req)
+    fn run_typed_request(self)
+            -> Result<ContainerStats, SwiftError> {
+        let resp = try!(check_status(try!(self.run_request())));
+        ContainerStats::from_headers(resp.headers())
     }
 }
 
 /*
- * Helper functions
+ * Post Container
  */
+pub struct PostContainer<A> {
+    container: String,
+    headers: Headers,
+    auth: Arc<A>
+}
 
-fn build_request(auth: &Auth, method: Method, path: String, headers: Headers)
-    -> Result<RequestBuilder, SwiftError>
-{
-    let req_builder = try!(
-        auth.build_request(
-            method, path, headers
-        ).map_err(SwiftError::Auth)
-    );
-    Ok(req_builder)
+impl<AS: Sized+Auth> PostContainer<AS> {
+    pub fn new(auth: Arc<AS>, container: String) -> PostContainer<AS> {
+        PostContainer {
+            container: container,
+            headers: Headers::new(),
+            auth: auth
+        }
+    }
+
+    pub fn metadata<K: Display, V: AsRef<str>>(
+        mut self, name: K, value: V
+    ) -> PostContainer<AS> {
+        set_metadata_header(&mut self.headers, MetadataKind::Container, name, value);
+        self
+    }
+
+    pub fn metadata_map(mut self, metadata: &Metadata) -> PostContainer<AS> {
+        metadata.set_headers(&mut self.headers, MetadataKind::Container);
+        self
+    }
+
+    pub fn remove_metadata<K: Display>(mut self, name: K) -> PostContainer<AS> {
+        remove_metadata_header(&mut self.headers, MetadataKind::Container, name);
+        self
+    }
+
+    // The key for signing temp URLs for objects in this container
+    pub fn temp_url_key<S: Into<String>>(mut self, key: S) -> PostContainer<AS> {
+        set_raw_metadata_header(&mut self.headers, MetadataKind::Container, TEMP_URL_KEY, key);
+        self
+    }
+
+    // A second key, so that keys can be rotated
+    pub fn temp_url_key_2<S: Into<String>>(mut self, key: S) -> PostContainer<AS> {
+        set_raw_metadata_header(&mut self.headers, MetadataKind::Container, TEMP_URL_KEY_2, key);
+        self
+    }
+
+    pub fn read_acl<S: Into<String>>(self, acl: S) -> PostContainer<AS> {
+        self.raw_header("X-Container-Read", acl)
+    }
+
+    pub fn write_acl<S: Into<String>>(self, acl: S) -> PostContainer<AS> {
+        self.raw_header("X-Container-Write", acl)
+    }
 }
 
-fn make_request(request: RequestBuilder)
-    -> Result<Response, SwiftError>
-{
-    let resp = match request.send() {
-        Ok(r) => r,
-        Err(e) => return Err(SwiftError::Http(e))
-    };
-    Ok(resp)
+impl<AS: Sized+Auth> SwiftRequestHeaders for PostContainer<AS> {
+    fn headers_mut(&mut self) -> &mut Headers {
+        &mut self.headers
+    }
+}
+
+impl<AS: Sized+Auth> RunSwiftRequest for PostContainer<AS> {
+    fn run_request(self)
+            -> Result<Response, SwiftError> {
+        let path = container_path(&self.container);
+        send_request(
+            self.auth.as_ref(), Method::Post, path, self.headers.clone()
+        )
+    }
+}
+
+impl<AS: Sized+Auth> RunTypedSwiftRequest for PostContainer<AS> {
+    type Output = ();
+
+    fn run_typed_request(self)
+            -> Result<(), SwiftError> {
+        try!(check_status(try!(self.run_request())));
+        Ok(())
+    }
+}
+
+/*
+ * Delete Container
+ */
+pub struct DeleteContainer<A> {
+    container: String,
+    headers: Headers,
+    auth: Arc<A>
+}
+
+impl<AS: Sized+Auth> DeleteContainer<AS> {
+    pub fn new(auth: Arc<AS>, container: String) -> DeleteContainer<AS> {
+        DeleteContainer {
+            container: container,
+            headers: Headers::new(),
+            auth: auth
+        }
+    }
+}
+
+impl<AS: Sized+Auth> SwiftRequestHeaders for DeleteContainer<AS> {
+    fn headers_mut(&mut self) -> &mut Headers {
+        &mut self.headers
+    }
+}
+
+impl<AS: Sized+Auth> RunSwiftRequest for DeleteContainer<AS> {
+    fn run_request(self)
+            -> Result<Response, SwiftError> {
+        let path = container_path(&self.container);
+        send_request(
+            self.auth.as_ref(), Method::Delete, path, self.headers.clone()
+        )
+    }
+}
+
+impl<AS: Sized+Auth> RunTypedSwiftRequest for DeleteContainer<AS> {
+    type Output = ();
+
+    // Deleting a container that still has objects in it is a Conflict
+    fn run_typed_request(self)
+            -> Result<(), SwiftError> {
+        try!(check_status(try!(self.run_request())));
+        Ok(())
+    }
+}
+
+/*
+ * Listing iterators, which follow the marker across pages of a listing
+ * and only fetch the next page when the current one runs out
+ */
+struct ListingState<T> {
+    marker: Option<String>,
+    page: VecDeque<T>,
+    done: bool
+}
+
+impl<T> ListingState<T> {
+    fn new(marker: Option<String>) -> ListingState<T> {
+        ListingState {
+            marker: marker,
+            page: VecDeque::new(),
+            done: false
+        }
+    }
+
+    fn next_entry<N, F>(&mut self, name: N, fetch: F)
+        -> Option<Result<T, SwiftError>>
+        where N: Fn(&T) -> String,
+              F: FnOnce(Option<String>) -> Result<Vec<T>, SwiftError>
+    {
+        if let Some(entry) = self.page.pop_front() {
+            return Some(Ok(entry))
+        }
+        if self.done {
+            return None
+        }
+        match fetch(self.marker.clone()) {
+            Ok(entries) => {
+                // An empty page means we've reached the end (the server
+                // may return fewer entries than we asked for before then)
+                match entries.last() {
+                    Some(last) => self.marker = Some(name(last)),
+                    None => self.done = true
+                };
+                self.page.extend(entries);
+                self.page.pop_front().map(Ok)
+            },
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+pub struct AccountIterator<A> {
+    end_marker: Option<String>,
+    prefix: Option<String>,
+    delimiter: Option<String>,
+    limit: u32,
+    state: ListingState<ContainerEntry>,
+    auth: Arc<A>
+}
+
+impl<AS: Sized+Auth> AccountIterator<AS> {
+    pub fn new(auth: Arc<AS>) -> AccountIterator<AS> {
+        AccountIterator {
+            end_marker: None,
+            prefix: None,
+            delimiter: None,
+            limit: 10000,
+            state: ListingState::new(None),
+            auth: auth
+        }
+    }
+
+    pub fn marker<S: Into<String>>(mut self, marker: S) -> AccountIterator<AS> {
+        self.state.marker = Some(marker.into());
+        self
+    }
+
+    pub fn end_marker<S: Into<String>>(mut self, end_marker: S) -> AccountIterator<AS> {
+        self.end_marker = Some(end_marker.into());
+        self
+    }
+
+    pub fn prefix<S: Into<String>>(mut self, prefix: S) -> AccountIterator<AS> {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    pub fn delimiter<S: Into<String>>(mut self, delimiter: S) -> AccountIterator<AS> {
+        self.delimiter = Some(delimiter.into());
+        self
+    }
+
+    // The number of entries fetched with each request
+    pub fn page_size(mut self, limit: u32) -> AccountIterator<AS> {
+        self.limit = limit;
+        self
+    }
+}
+
+impl<AS: Sized+Auth> Iterator for AccountIterator<AS> {
+    type Item = Result<ContainerEntry, SwiftError>;
+
+    fn next(&mut self) -> Option<Result<ContainerEntry, SwiftError>> {
+        let auth = self.auth.clone();
+        let end_marker = self.end_marker.clone();
+        let prefix = self.prefix.clone();
+        let delimiter = self.delimiter.clone();
+        let limit = self.limit;
+        self.state.next_entry(|c| String::from(c.name()), |marker| {
+            let mut ga = GetAccount::new(auth);
+            ga.marker = marker;
+            ga.end_marker = end_marker;
+            ga.prefix = prefix;
+            ga.delimiter = delimiter;
+            ga.limit = limit;
+            ga.run_typed_request().map(|l| l.containers)
+        })
+    }
+}
+
+pub struct ContainerIterator<A> {
+    container: String,
+    end_marker: Option<String>,
+    prefix: Option<String>,
+    delimiter: Option<String>,
+    limit: u32,
+    state: ListingState<ObjectEntry>,
+    auth: Arc<A>
+}
+
+impl<AS: Sized+Auth> ContainerIterator<AS> {
+    pub fn new(auth: Arc<AS>, container: String) -> ContainerIterator<AS> {
+        ContainerIterator {
+            container: container,
+            end_marker: None,
+            prefix: None,
+            delimiter: None,
+            limit: 10000,
+            state: ListingState::new(None),
+            auth: auth
+        }
+    }
+
+    pub fn marker<S: Into<String>>(mut self, marker: S) -> ContainerIterator<AS> {
+        self.state.marker = Some(marker.into());
+        self
+    }
+
+    pub fn end_marker<S: Into<String>>(mut self, end_marker: S) -> ContainerIterator<AS> {
+        self.end_marker = Some(end_marker.into());
+        self
+    }
+
+    pub fn prefix<S: Into<String>>(mut self, prefix: S) -> ContainerIterator<AS> {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    pub fn delimiter<S: Into<String>>(mut self, delimiter: S) -> ContainerIterator<AS> {
+        self.delimiter = Some(delimiter.into());
+        self
+    }
+
+    // The number of entries fetched with each request
+    pub fn page_size(mut self, limit: u32) -> ContainerIterator<AS> {
+        self.limit = limit;
+        self
+    }
+}
+
+impl<AS: Sized+Auth> Iterator for ContainerIterator<AS> {
+    type Item = Result<ObjectEntry, SwiftError>;
+
+    fn next(&mut self) -> Option<Result<ObjectEntry, SwiftError>> {
+        let auth = self.auth.clone();
+        let container = self.container.clone();
+        let end_marker = self.end_marker.clone();
+        let prefix = self.prefix.clone();
+        let delimiter = self.delimiter.clone();
+        let limit = self.limit;
+        self.state.next_entry(|o| String::from(o.name()), |marker| {
+            let mut gc = GetContainer::new(auth, container);
+            gc.marker = marker;
+            gc.end_marker = end_marker;
+            gc.prefix = prefix;
+            gc.delimiter = delimiter;
+            gc.limit = limit;
+            gc.run_typed_request().map(|l| l.objects)
+        })
+    }
+}
+
+/*
+ * Get Object
+ */
+#[derive(Debug)]
+pub enum GetObjectResult {
+    // 200, with the whole object to read from the response
+    Full(ObjectMetadata, Response),
+    // 206 for a single range, to read from the response
+    Partial(ObjectMetadata, ContentRange, Response),
+    // 206 for several ranges, parsed from a multipart/byteranges body
+    Parts(ObjectMetadata, Vec<ObjectPart>),
+    // 304, so the copy the caller already has is still current
+    NotModified(ObjectMetadata)
+}
+
+pub struct GetObject<A> {
+    container: String,
+    object: String,
+    multipart_manifest_get: bool,
+    ranges: Vec<ByteRangeSpec>,
+    if_match: Vec<String>,
+    if_none_match: Vec<String>,
+    if_modified_since: Option<DateTime<UTC>>,
+    if_unmodified_since: Option<DateTime<UTC>>,
+    verify_md5: bool,
+    headers: Headers,
+    auth: Arc<A>
+}
+
+impl<AS: Sized+Auth> GetObject<AS> {
+    pub fn new(
+        auth: Arc<AS>, container: String, object: String
+    ) -> GetObject<AS> {
+        GetObject {
+            container: container,
+            object: object,
+            multipart_manifest_get: false,
+            ranges: Vec::new(),
+            if_match: Vec::new(),
+            if_none_match: Vec::new(),
+            if_modified_since: None,
+            if_unmodified_since: None,
+            verify_md5: false,
+            headers: Headers::new(),
+            auth: auth
+        }
+    }
+
+    // Get the manifest of a large object, rather than its contents
+    pub fn multipart_manifest_get(
+        mut self, multipart_manifest_get: bool
+    ) -> GetObject<AS> {
+        self.multipart_manifest_get = multipart_manifest_get;
+        self
+    }
+
+    /*
+     * Each range added is requested, e.g. ByteRangeSpec::FromTo(0, 99)
+     * for the first 100 bytes
+     */
+    pub fn range(mut self, range: ByteRangeSpec) -> GetObject<AS> {
+        self.ranges.push(range);
+        self
+    }
+
+    pub fn if_match<S: Into<String>>(mut self, etag: S) -> GetObject<AS> {
+        self.if_match.push(etag.into());
+        self
+    }
+
+    pub fn if_none_match<S: Into<String>>(mut self, etag: S) -> GetObject<AS> {
+        self.if_none_match.push(etag.into());
+        self
+    }
+
+    pub fn if_modified_since(mut self, date: DateTime<UTC>) -> GetObject<AS> {
+        self.if_modified_since = Some(date);
+        self
+    }
+
+    pub fn if_unmodified_since(mut self, date: DateTime<UTC>) -> GetObject<AS> {
+        self.if_unmodified_since = Some(date);
+        self
+    }
+
+    // Check the MD5 of the data when using write_to
+    pub fn verify_md5(mut self, verify_md5: bool) -> GetObject<AS> {
+        self.verify_md5 = verify_md5;
+        self
+    }
+
+    /*
+     * Stream the object into writer, computing its MD5 on the way. The
+     * MD5 can't be checked for large objects, whose etags are quoted,
+     * or when only part of the object was asked for.
+     */
+    pub fn write_to<W: Write>(self, writer: &mut W)
+        -> Result<ObjectMetadata, SwiftError>
+    {
+        let verify = self.verify_md5 && self.ranges.is_empty() &&
+            !self.multipart_manifest_get;
+        let name = format!("{}/{}", self.container, self.object);
+        let resp = try!(check_status(try!(self.run_request())));
+        let meta = try!(ObjectMetadata::from_headers(resp.headers()));
+        let mut reader = Md5Reader::new(resp);
+        try!(io::copy(&mut reader, writer).map_err(SwiftError::Io));
+        if let (true, Some(ref etag)) = (verify, meta.etag.clone()) {
+            if !is_manifest_etag(etag) {
+                try!(check_md5(&name, etag, &reader.digest().hex()));
+            }
+        }
+        Ok(meta)
+    }
+}
+
+impl<AS: Sized+Auth> SwiftRequestHeaders for GetObject<AS> {
+    fn headers_mut(&mut self) -> &mut Headers {
+        &mut self.headers
+    }
+}
+
+impl<AS: Sized+Auth> RunSwiftRequest for GetObject<AS> {
+    fn run_request(self)
+        -> Result<Response, SwiftError>
+    {
+        let mut path = object_path(&self.container, &self.object);
+        if self.multipart_manifest_get {
+            path = path + &format!("?{}={}", &"multipart-manifest", &"get");
+        };
+
+        let mut headers = self.headers.clone();
+        if !self.ranges.is_empty() {
+            headers.set(Range::Bytes(self.ranges.clone()));
+        };
+        if !self.if_match.is_empty() {
+            headers.set_raw("If-Match", vec![etag_list(&self.if_match).into_bytes()]);
+        };
+        if !self.if_none_match.is_empty() {
+            headers.set_raw(
+                "If-None-Match", vec![etag_list(&self.if_none_match).into_bytes()]);
+        };
+        if let Some(ref date) = self.if_modified_since {
+            headers.set_raw(
+                "If-Modified-Since", vec![format_http_date(date).into_bytes()]);
+        };
+        if let Some(ref date) = self.if_unmodified_since {
+            headers.set_raw(
+                "If-Unmodified-Since", vec![format_http_date(date).into_bytes()]);
+        };
+
+        send_request(self.auth.as_ref(), Method::Get, path, headers)
+    }
+}
+
+impl<AS: Sized+Auth> RunTypedSwiftRequest for GetObject<AS> {
+    type Output = GetObjectResult;
+
+    fn run_typed_request(self)
+            -> Result<GetObjectResult, SwiftError> {
+        let resp = try!(self.run_request());
+        // Not a success, but not a failure either
+        if *resp.status() == StatusCode::NotModified {
+            let meta = try!(ObjectMetadata::from_headers(resp.headers()));
+            return Ok(GetObjectResult::NotModified(meta))
+        }
+        let mut resp = try!(check_status(resp));
+        let meta = try!(ObjectMetadata::from_headers(resp.headers()));
+        if *resp.status() != StatusCode::PartialContent {
+            return Ok(GetObjectResult::Full(meta, resp))
+        }
+
+        let content_type = header_string(resp.headers(), "Content-Type");
+        if let Some(boundary) = content_type.and_then(|t| byteranges_boundary(&t)) {
+            let mut body = Vec::new();
+            if let Err(e) = resp.read_to_end(&mut body) {
+                return Err(SwiftError::Io(e))
+            };
+            let parts = try!(parse_byteranges(&body, &boundary));
+            return Ok(GetObjectResult::Parts(meta, parts))
+        }
+        match header_string(resp.headers(), "Content-Range").and_then(
+                |r| ContentRange::parse(&r)) {
+            Some(range) => Ok(GetObjectResult::Partial(meta, range, resp)),
+            None => Err(SwiftError::Parse(String::from(
+                "Partial content without a Content-Range")))
+        }
+    }
+}
+
+/*
+ * Put Object
+ */
+
+// How the MD5 of the body is known, to check against the returned etag
+enum BodyChecksum {
+    Known(String),
+    Streamed(Md5Digest)
+}
+
+pub struct PutObject<A, T: Into<Body>> {
+    container: String,
+    object: String,
+    multipart_manifest_put: bool,
+    checksum: Option<BodyChecksum>,
+    headers: Headers,
+    body: T,
+    replay_body: Option<T>,
+    auth: Arc<A>
+}
+
+impl<AS: Sized+Auth, T: Into<Body>> PutObject<AS, T> {
+    pub fn new(
+        auth: Arc<AS>, container: String, object: String, body: T
+    ) -> PutObject<AS, T> {
+        PutObject {
+            container: container,
+            object: object,
+            multipart_manifest_put: false,
+            checksum: None,
+            headers: Headers::new(),
+            body: body,
+            replay_body: None,
+            auth: auth
+        }
+    }
+
+    // Upload the body as a static large object manifest
+    pub fn multipart_manifest_put(
+        mut self, multipart_manifest_put: bool
+    ) -> PutObject<AS, T> {
+        self.multipart_manifest_put = multipart_manifest_put;
+        self
+    }
+
+    pub fn metadata<K: Display, V: AsRef<str>>(
+        mut self, name: K, value: V
+    ) -> PutObject<AS, T> {
+        set_metadata_header(&mut self.headers, MetadataKind::Object, name, value);
+        self
+    }
+
+    pub fn metadata_map(mut self, metadata: &Metadata) -> PutObject<AS, T> {
+        metadata.set_headers(&mut self.headers, MetadataKind::Object);
+        self
+    }
+}
+
+impl<AS: Sized+Auth, T: Into<Body>> SwiftRequestHeaders for PutObject<AS, T> {
+    fn headers_mut(&mut self) -> &mut Headers {
+        &mut self.headers
+    }
+}
+
+impl<AS: Sized+Auth, T: Into<Body>+AsRef<[u8]>> PutObject<AS, T> {
+    /*
+     * Send the MD5 of the body as the ETag, so that Swift rejects the
+     * data if it's corrupted on the way. Not for SLO manifests, whose
+     * etag is made from their segments.
+     */
+    pub fn checksum(mut self) -> PutObject<AS, T> {
+        let md5 = md5_hex(self.body.as_ref());
+        self.headers.set_raw("ETag", vec![md5.clone().into_bytes()]);
+        self.checksum = Some(BodyChecksum::Known(md5));
+        self
+    }
+}
+
+impl<AS: Sized+Auth, T: Into<Body>+Clone> PutObject<AS, T> {
+    /*
+     * Keep a copy of the body so that the request can be replayed if
+     * the auth token is rejected and we have to re-authenticate
+     */
+    pub fn replayable(mut self) -> PutObject<AS, T> {
+        self.replay_body = Some(self.body.clone());
+        self
+    }
+}
+
+impl<AS: Sized+Auth, T: Into<Body>> RunSwiftRequest for PutObject<AS, T> {
+    fn run_request(self)
+            -> Result<Response, SwiftError> {
+        let mut path = object_path(&self.container, &self.object);
+        if self.multipart_manifest_put {
+            path = path + &format!("?{}={}", &"multipart-manifest", &"put");
+        };
+
+        let auth = self.auth.clone();
+        let headers = self.headers.clone();
+        let mut body = Some(self.body);
+        let mut replay_body = self.replay_body;
+        let resp = try!(send_with_reauth(auth.as_ref(), || {
+            // Once the body has been sent it can only be sent again
+            // if we kept a copy of it
+            let b = match body.take().or_else(|| replay_body.take()) {
+                Some(b) => b,
+                None => return Ok(None)
+            };
+            let (put_req, token) = try!(build_request(
+                auth.as_ref(), Method::Put, path.clone(), headers.clone()
+            ));
+            Ok(Some((put_req.body(b), token)))
+        }));
+
+        if let (true, Some(checksum)) = (resp.status().is_success(), self.checksum) {
+            let md5 = match checksum {
+                BodyChecksum::Known(md5) => md5,
+                BodyChecksum::Streamed(digest) => digest.hex()
+            };
+            let name = format!("{}/{}", self.container, self.object);
+            match header_string(resp.headers(), "Etag") {
+                Some(etag) => try!(check_md5(&name, &etag, &md5)),
+                None => return Err(SwiftError::Parse(
+                    format!("No etag returned for {}", name)))
+            };
+        };
+        Ok(resp)
+    }
+}
+
+/*
+ * Head Object
+ */
+pub struct HeadObject<A> {
+    container: String,
+    object: String,
+    headers: Headers,
+    auth: Arc<A>
+}
+
+impl<AS: Sized+Auth> HeadObject<AS> {
+    pub fn new(
+        auth: Arc<AS>, container: String, object: String
+    ) -> HeadObject<AS> {
+        HeadObject {
+            container: container,
+            object: object,
+            headers: Headers::new(),
+            auth: auth
+        }
+    }
+}
+
+impl<AS: Sized+Auth> SwiftRequestHeaders for HeadObject<AS> {
+    fn headers_mut(&mut self) -> &mut Headers {
+        &mut self.headers
+    }
+}
+
+impl<AS: Sized+Auth> RunSwiftRequest for HeadObject<AS> {
+    fn run_request(self)
+        -> Result<Response, SwiftError>
+    {
+        let path = object_path(&self.container, &self.object);
+        send_request(
+            self.auth.as_ref(), Method::Head, path, self.headers.clone()
+        )
+    }
+}
+
+impl<AS: Sized+Auth> RunTypedSwiftRequest for HeadObject<AS> {
+    type Output = ObjectMetadata;
+
+    fn run_typed_request(self)
+            -> Result<ObjectMetadata, SwiftError> {
+        let resp = try!(check_status(try!(self.run_request())));
+        ObjectMetadata::from_headers(resp.headers())
+    }
+}
+
+/*
+ * Post Object
+ */
+pub struct PostObject<A> {
+    container: String,
+    object: String,
+    headers: Headers,
+    auth: Arc<A>
+}
+
+impl<AS: Sized+Auth> PostObject<AS> {
+    pub fn new(
+        auth: Arc<AS>, container: String, object: String
+    ) -> PostObject<AS> {
+        PostObject {
+            container: container,
+            object: object,
+            headers: Headers::new(),
+            auth: auth
+        }
+    }
+
+    // A POST replaces all of the existing object metadata
+    pub fn metadata<K: Display, V: AsRef<str>>(
+        mut self, name: K, value: V
+    ) -> PostObject<AS> {
+        set_metadata_header(&mut self.headers, MetadataKind::Object, name, value);
+        self
+    }
+
+    pub fn metadata_map(mut self, metadata: &Metadata) -> PostObject<AS> {
+        metadata.set_headers(&mut self.headers, MetadataKind::Object);
+        self
+    }
+
+    pub fn content_type<S: Into<String>>(self, content_type: S) -> PostObject<AS> {
+        self.raw_header("Content-Type", content_type)
+    }
+}
+
+impl<AS: Sized+Auth> SwiftRequestHeaders for PostObject<AS> {
+    fn headers_mut(&mut self) -> &mut Headers {
+        &mut self.headers
+    }
+}
+
+impl<AS: Sized+Auth> RunSwiftRequest for PostObject<AS> {
+    fn run_request(self)
+        -> Result<Response, SwiftError>
+    {
+        let path = object_path(&self.container, &self.object);
+        send_request(
+            self.auth.as_ref(), Method::Post, path, self.headers.clone()
+        )
+    }
+}
+
+impl<AS: Sized+Auth> RunTypedSwiftRequest for PostObject<AS> {
+    type Output = ();
+
+    fn run_typed_request(self)
+            -> Result<(), SwiftError> {
+        try!(check_status(try!(self.run_request())));
+        Ok(())
+    }
+}
+
+/*
+ * Delete Object
+ */
+pub struct DeleteObject<A> {
+    container: String,
+    object: String,
+    multipart_manifest_delete: bool,
+    headers: Headers,
+    auth: Arc<A>
+}
+
+impl<AS: Sized+Auth> DeleteObject<AS> {
+    pub fn new(
+        auth: Arc<AS>, container: String, object: String
+    ) -> DeleteObject<AS> {
+        DeleteObject {
+            container: container,
+            object: object,
+            multipart_manifest_delete: false,
+            headers: Headers::new(),
+            auth: auth
+        }
+    }
+
+    // Delete a static large object's segments along with its manifest
+    pub fn multipart_manifest_delete(
+        mut self, multipart_manifest_delete: bool
+    ) -> DeleteObject<AS> {
+        self.multipart_manifest_delete = multipart_manifest_delete;
+        self
+    }
+}
+
+impl<AS: Sized+Auth> SwiftRequestHeaders for DeleteObject<AS> {
+    fn headers_mut(&mut self) -> &mut Headers {
+        &mut self.headers
+    }
+}
+
+impl<AS: Sized+Auth> RunSwiftRequest for DeleteObject<AS> {
+    fn run_request(self)
+        -> Result<Response, SwiftError>
+    {
+        let mut path = object_path(&self.container, &self.object);
+        if self.multipart_manifest_delete {
+            path = path + &format!("?{}={}", &"multipart-manifest", &"delete");
+        };
+
+        send_request(
+            self.auth.as_ref(), Method::Delete, path, self.headers.clone()
+        )
+    }
+}
+
+impl<AS: Sized+Auth> RunTypedSwiftRequest for DeleteObject<AS> {
+    type Output = ();
+
+    fn run_typed_request(self)
+            -> Result<(), SwiftError> {
+        try!(check_status(try!(self.run_request())));
+        Ok(())
+    }
+}
+
+/*
+ * Bulk Delete
+ */
+pub struct BulkDelete<A, I: Iterator<Item=String>> {
+    // Paths as "container/object", or "container" for empty containers
+    paths: I,
+    max_deletes_per_request: u64,
+    headers: Headers,
+    auth: Arc<A>
+}
+
+impl<AS: Sized+Auth, I: Iterator<Item=String>> BulkDelete<AS, I> {
+    pub fn new(auth: Arc<AS>, paths: I) -> BulkDelete<AS, I> {
+        BulkDelete {
+            paths: paths,
+            max_deletes_per_request: DEFAULT_MAX_DELETES_PER_REQUEST,
+            headers: Headers::new(),
+            auth: auth
+        }
+    }
+
+    // The paths are sent in batches of at most this many
+    pub fn max_deletes_per_request(
+        mut self, max_deletes_per_request: u64
+    ) -> BulkDelete<AS, I> {
+        self.max_deletes_per_request = max_deletes_per_request;
+        self
+    }
+
+    // Use the cluster's batch limit, if it has one
+    pub fn capabilities(mut self, caps: &Capabilities) -> BulkDelete<AS, I> {
+        if let Some(max) = caps.bulk_delete.as_ref().and_then(|b| b.max_deletes_per_request) {
+            self.max_deletes_per_request = max;
+        }
+        self
+    }
+}
+
+impl<AS: Sized+Auth, I: Iterator<Item=String>> SwiftRequestHeaders for BulkDelete<AS, I> {
+    fn headers_mut(&mut self) -> &mut Headers {
+        &mut self.headers
+    }
+}
+
+impl<AS: Sized+Auth, I: Iterator<Item=String>> RunTypedSwiftRequest for BulkDelete<AS, I> {
+    type Output = BulkDeleteResult;
+
+    /*
+     * Each batch is POSTed to the account as newline separated, quoted
+     * paths, and the reports are combined
+     */
+    fn run_typed_request(mut self)
+            -> Result<BulkDeleteResult, SwiftError> {
+        let mut headers = self.headers.clone();
+        headers.set_raw("Accept", vec![b"application/json".to_vec()]);
+        headers.set_raw("Content-Type", vec![b"text/plain".to_vec()]);
+        let batch_size = cmp::max(self.max_deletes_per_request, 1) as usize;
+        let auth = self.auth.as_ref();
+
+        let mut result = BulkDeleteResult::default();
+        loop {
+            let batch: Vec<String> = self.paths.by_ref().take(batch_size).collect();
+            if batch.is_empty() {
+                break;
+            }
+            // '/' and '%' are quoted too, and Swift unquotes the whole line
+            let body = batch.iter().map(|p| {
+                format!("/{}", utf8_percent_encode(p, PATH_SEGMENT_ENCODE_SET))
+            }).collect::<Vec<String>>().join("\n");
+            let resp = try!(send_with_reauth(auth, || {
+                let (req, token) = try!(build_request(
+                    auth, Method::Post, String::from("?bulk-delete"), headers.clone()
+                ));
+                Ok(Some((req.body(body.clone()), token)))
+            }));
+            try!(parse_bulk_delete(try!(check_status(resp)), batch, &mut result));
+        }
+        Ok(result)
+    }
+}
+
+/*
+ * Copy Object
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CopyMethod {
+    // COPY the source object, with a Destination header
+    Copy,
+    // PUT the destination object, with an X-Copy-From header
+    PutCopyFrom
+}
+
+pub struct CopyObject<A> {
+    container: String,
+    object: String,
+    destination_container: String,
+    destination_object: String,
+    destination_account: Option<String>,
+    method: CopyMethod,
+    fresh_metadata: bool,
+    multipart_manifest_get: bool,
+    headers: Headers,
+    auth: Arc<A>
+}
+
+impl<AS: Sized+Auth> CopyObject<AS> {
+    pub fn new(
+        auth: Arc<AS>, container: String, object: String,
+        destination_container: String, destination_object: String
+    ) -> CopyObject<AS> {
+        CopyObject {
+            container: container,
+            object: object,
+            destination_container: destination_container,
+            destination_object: destination_object,
+            destination_account: None,
+            method: CopyMethod::Copy,
+            fresh_metadata: false,
+            multipart_manifest_get: false,
+            headers: Headers::new(),
+            auth: auth
+        }
+    }
+
+    pub fn method(mut self, method: CopyMethod) -> CopyObject<AS> {
+        self.method = method;
+        self
+    }
+
+    // Cross-account copies always use the COPY method
+    pub fn destination_account<S: Into<String>>(
+        mut self, account: S
+    ) -> CopyObject<AS> {
+        self.destination_account = Some(account.into());
+        self
+    }
+
+    // Don't keep the source object's metadata
+    pub fn fresh_metadata(mut self, fresh_metadata: bool) -> CopyObject<AS> {
+        self.fresh_metadata = fresh_metadata;
+        self
+    }
+
+    // Copy a large object's manifest, rather than its contents
+    pub fn multipart_manifest_get(
+        mut self, multipart_manifest_get: bool
+    ) -> CopyObject<AS> {
+        self.multipart_manifest_get = multipart_manifest_get;
+        self
+    }
+
+    // Merged with the source metadata, unless fresh_metadata is set
+    pub fn metadata<K: Display, V: AsRef<str>>(
+        mut self, name: K, value: V
+    ) -> CopyObject<AS> {
+        set_metadata_header(&mut self.headers, MetadataKind::Object, name, value);
+        self
+    }
+
+    pub fn metadata_map(mut self, metadata: &Metadata) -> CopyObject<AS> {
+        metadata.set_headers(&mut self.headers, MetadataKind::Object);
+        self
+    }
+}
+
+impl<AS: Sized+Auth> SwiftRequestHeaders for CopyObject<AS> {
+    fn headers_mut(&mut self) -> &mut Headers {
+        &mut self.headers
+    }
+}
+
+impl<AS: Sized+Auth> RunSwiftRequest for CopyObject<AS> {
+    fn run_request(self)
+        -> Result<Response, SwiftError>
+    {
+        let mut headers = self.headers.clone();
+        // Both headers are unquoted by Swift, like the request path
+        let source = encode_path(
+            &format!("{}/{}", self.container, self.object));
+        let destination = encode_path(
+            &format!("{}/{}", self.destination_container, self.destination_object));
+        let method = match self.destination_account {
+            Some(_) => CopyMethod::Copy,
+            None => self.method
+        };
+        let (m, mut path) = match method {
+            CopyMethod::Copy => {
+                headers.set_raw("Destination", vec![destination.into_bytes()]);
+                if let Some(ref account) = self.destination_account {
+                    headers.set_raw(
+                        "Destination-Account", vec![account.clone().into_bytes()]);
+                };
+                (Method::Extension(String::from("COPY")),
+                 object_path(&self.container, &self.object))
+            },
+            CopyMethod::PutCopyFrom => {
+                headers.set_raw("X-Copy-From", vec![source.into_bytes()]);
+                // Otherwise the body would be the object we're creating
+                headers.set(ContentLength(0));
+                (Method::Put,
+                 object_path(&self.destination_container, &self.destination_object))
+            }
+        };
+        if self.fresh_metadata {
+            headers.set_raw("X-Fresh-Metadata", vec![b"true".to_vec()]);
+        };
+        if self.multipart_manifest_get {
+            path = path + &format!("?{}={}", &"multipart-manifest", &"get");
+        };
+
+        send_request(self.auth.as_ref(), m, path, headers)
+    }
+}
+
+impl<AS: Sized+Auth> RunTypedSwiftRequest for CopyObject<AS> {
+    type Output = CopyObjectResult;
+
+    fn run_typed_request(self)
+            -> Result<CopyObjectResult, SwiftError> {
+        let resp = try!(check_status(try!(self.run_request())));
+        Ok(CopyObjectResult::from_headers(resp.headers()))
+    }
+}
+
+/*
+ * Helper functions
+ */
+
+define_encode_set! {
+    // Swift unquotes paths, so a literal '%' has to be quoted too
+    pub PATH_ENCODE_SET = [DEFAULT_ENCODE_SET] | {'%'}
+}
+
+// Quote a "container/object" style path, leaving the slashes alone
+pub fn encode_path(path: &str) -> String {
+    utf8_percent_encode(path, PATH_ENCODE_SET).to_string()
+}
+
+fn container_path(container: &String) -> String {
+    format!("/{}", encode_path(container))
+}
+
+fn object_path(container: &String, object: &String) -> String {
+    format!("{}/{}", container_path(container), encode_path(object))
+}
+
+// Quoted and comma separated, for If-Match and If-None-Match
+fn etag_list(etags: &Vec<String>) -> String {
+    etags.iter().map(|e| {
+        if e == "*" || e.starts_with('"') {
+            e.clone()
+        } else {
+            format!("\"{}\"", e)
+        }
+    }).collect::<Vec<String>>().join(", ")
+}
+
+fn format_http_date(date: &DateTime<UTC>) -> String {
+    date.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+fn encode_query_value<V: Display>(value: &V) -> String {
+    let v = format!("{}", value);
+    form_urlencoded::byte_serialize(v.as_bytes()).collect()
+}
+
+// The request, and the token it was built with
+fn build_request(auth: &Auth, method: Method, path: String, headers: Headers)
+    -> Result<(RequestBuilder, Option<String>), SwiftError>
+{
+    let req_builder = try!(
+        auth.build_request_with_token(
+            method, path, headers
+        ).map_err(SwiftError::Auth)
+    );
+    Ok(req_builder)
+}
+
+fn make_request(request: RequestBuilder)
+    -> Result<Response, SwiftError>
+{
+    let resp = match request.send() {
+        Ok(r) => r,
+        Err(e) => return Err(SwiftError::Http(e))
+    };
+    // A 401 means the server rejected our token, which callers need to be
+    // able to tell apart from any other failed request
+    if *resp.status() == StatusCode::Unauthorized {
+        error!("Auth token rejected: {}", resp.url());
+        let err_msg = format!("{} returned {}", resp.url(), resp.status());
+        return Err(SwiftError::Auth(AuthError::Unauthorized(err_msg)))
+    };
+    Ok(resp)
+}
+
+/*
+ * Send a request without a body, re-authenticating and replaying it once
+ * if the auth token is rejected
+ */
+fn send_request(auth: &Auth, method: Method, path: String, headers: Headers)
+    -> Result<Response, SwiftError>
+{
+    send_with_reauth(auth, || {
+        let req = try!(build_request(
+            auth, method.clone(), path.clone(), headers.clone()
+        ));
+        Ok(Some(req))
+    })
+}
+
+/*
+ * The build function is called for each attempt, and should return None
+ * if the request can't be built again (e.g. because the body has been
+ * consumed)
+ */
+fn send_with_reauth<F>(auth: &Auth, build: F)
+    -> Result<Response, SwiftError>
+    where F: FnMut() -> Result<Option<(RequestBuilder, Option<String>)>, SwiftError>
+{
+    replay_on_reject(auth, build, make_request)
+}
+
+fn replay_on_reject<R, T, F, S>(auth: &Auth, mut build: F, mut send: S)
+    -> Result<T, SwiftError>
+    where F: FnMut() -> Result<Option<(R, Option<String>)>, SwiftError>,
+          S: FnMut(R) -> Result<T, SwiftError>
+{
+    let mut rejected: Option<String> = None;
+    while let Some((req, token)) = try!(build()) {
+        match send(req) {
+            Err(SwiftError::Auth(AuthError::Unauthorized(msg))) => {
+                // Only the token that was actually rejected is thrown away
+                let invalidated = match token {
+                    Some(ref t) => auth.invalidate_token(t),
+                    None => false
+                };
+                if rejected.is_some() || !invalidated {
+                    return Err(SwiftError::Auth(AuthError::Unauthorized(msg)))
+                };
+                debug!("Auth token rejected, re-authenticating: {}", msg);
+                rejected = Some(msg);
+            },
+            r => return r
+        }
+    }
+    error!("Auth token rejected and the request cannot be replayed");
+    let err_msg = match rejected {
+        Some(msg) => format!("{} (request cannot be replayed)", msg),
+        None => String::from("Request could not be built")
+    };
+    Err(SwiftError::Auth(AuthError::Unauthorized(err_msg)))
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::header::Headers;
+    use hyper::method::Method;
+    use reqwest::RequestBuilder;
+    use std::sync::Mutex;
+
+    use auth::errors::AuthError;
+    use auth::sessions::Auth;
+    use client::errors::SwiftError;
+    use client::request::replay_on_reject;
+
+    // Hands out a new token each time the current one is invalidated
+    struct MockAuth {
+        generation: Mutex<u32>,
+        invalidated: Mutex<Vec<String>>
+    }
+
+    impl MockAuth {
+        fn new() -> MockAuth {
+            MockAuth {
+                generation: Mutex::new(0),
+                invalidated: Mutex::new(Vec::new())
+            }
+        }
+
+        fn token(&self) -> String {
+            format!("token-{}", *self.generation.lock().unwrap())
+        }
+
+        fn invalidated(&self) -> Vec<String> {
+            self.invalidated.lock().unwrap().clone()
+        }
+    }
+
+    impl Auth for MockAuth {
+        fn build_request(&self, _m: Method, _path: String, _headers: Headers)
+                -> Result<RequestBuilder, AuthError> {
+            Err(AuthError::Fail(String::from("Requests are built by the tests")))
+        }
+
+        fn invalidate_token(&self, rejected: &str) -> bool {
+            self.invalidated.lock().unwrap().push(String::from(rejected));
+            if self.token() == rejected {
+                *self.generation.lock().unwrap() += 1;
+            }
+            true
+        }
+    }
+
+    fn unauthorized() -> SwiftError {
+        SwiftError::Auth(AuthError::Unauthorized(String::from("401 Unauthorized")))
+    }
+
+    fn is_unauthorized(result: &Result<String, SwiftError>) -> bool {
+        match *result {
+            Err(SwiftError::Auth(AuthError::Unauthorized(_))) => true,
+            _ => false
+        }
+    }
+
+    #[test]
+    fn replay_after_reject() {
+        let auth = MockAuth::new();
+        let result = replay_on_reject(&auth, || Ok(Some((auth.token(), Some(auth.token())))),
+                                      |token| {
+            if token == "token-0" { Err(unauthorized()) } else { Ok(token) }
+        });
+        assert_eq!(result.ok(), Some(String::from("token-1")));
+        assert_eq!(auth.invalidated(), vec![String::from("token-0")]);
+    }
+
+    #[test]
+    fn only_replayed_once() {
+        let auth = MockAuth::new();
+        let mut sent = 0;
+        let result = replay_on_reject(&auth, || Ok(Some((auth.token(), Some(auth.token())))),
+                                      |_: String| {
+            sent += 1;
+            Err(unauthorized())
+        });
+        assert!(is_unauthorized(&result));
+        assert_eq!(sent, 2);
+        assert_eq!(auth.invalidated(), vec![String::from("token-0")]);
+    }
+
+    #[test]
+    fn not_replayable() {
+        let auth = MockAuth::new();
+        let mut body = Some(String::from("body"));
+        let result = replay_on_reject(&auth, || {
+            Ok(body.take().map(|b| (b, Some(auth.token()))))
+        }, |_: String| Err(unauthorized()));
+        match result {
+            Err(SwiftError::Auth(AuthError::Unauthorized(msg))) =>
+                assert!(msg.contains("cannot be replayed")),
+            _ => panic!("Expected the rejection to be returned")
+        };
+        // The token is still thrown away, for the next request
+        assert_eq!(auth.invalidated(), vec![String::from("token-0")]);
+    }
+
+    #[test]
+    fn no_token_to_invalidate() {
+        let auth = MockAuth::new();
+        let mut sent = 0;
+        let result = replay_on_reject(&auth, || Ok(Some((auth.token(), None))),
+                                      |_: String| {
+            sent += 1;
+            Err(unauthorized())
+        });
+        assert!(is_unauthorized(&result));
+        assert_eq!(sent, 1);
+        assert!(auth.invalidated().is_empty());
+    }
+
+    #[test]
+    fn other_errors_not_replayed() {
+        let auth = MockAuth::new();
+        let mut sent = 0;
+        let result = replay_on_reject(&auth, || Ok(Some((auth.token(), Some(auth.token())))),
+                                      |_: String| {
+            sent += 1;
+            Err(SwiftError::NotFound(String::from("404 Not Found")))
+        });
+        match result {
+            Err(SwiftError::NotFound(_)) => {},
+            _ => panic!("Expected the 404 to be returned")
+        };
+        assert_eq!(sent, 1);
+        assert!(auth.invalidated().is_empty());
+    }
 }
@@ -0,0 +1,82 @@
+use chrono::{DateTime, NaiveDateTime, UTC};
+use hyper::header::Headers;
+use reqwest::{Response, StatusCode};
+use std::io::Read;
+use std::str::FromStr;
+
+use client::errors::SwiftError;
+
+/*
+ * Helpers for reading Swift responses
+ */
+
+// Turn any unsuccessful response into an error
+pub fn check_status(mut resp: Response) -> Result<Response, SwiftError> {
+    if resp.status().is_success() {
+        return Ok(resp)
+    }
+    let status = resp.status().clone();
+    let url = resp.url().to_string();
+    // The body is just a short explanation, if there is one at all
+    let mut body = String::new();
+    let _ = resp.read_to_string(&mut body);
+    error!("{} returned {}", url, status);
+    let err_msg = format!("{} {}", url, body.trim());
+    match status {
+        // e.g. the container or object doesn't exist
+        StatusCode::NotFound => Err(SwiftError::NotFound(err_msg)),
+        // e.g. deleting a container that isn't empty
+        StatusCode::Conflict => Err(SwiftError::Conflict(err_msg)),
+        // The ETag sent with a PUT didn't match the data received
+        StatusCode::UnprocessableEntity => Err(SwiftError::ChecksumMismatch(err_msg)),
+        _ => Err(SwiftError::Status(status, err_msg))
+    }
+}
+
+pub fn read_body(resp: &mut Response) -> Result<String, SwiftError> {
+    let mut body = String::new();
+    match resp.read_to_string(&mut body) {
+        Ok(_) => Ok(body),
+        Err(e) => Err(SwiftError::Io(e))
+    }
+}
+
+pub fn header_string(headers: &Headers, name: &str) -> Option<String> {
+    match headers.get_raw(name) {
+        Some(values) if !values.is_empty() => {
+            String::from_utf8(values[0].clone()).ok()
+        },
+        _ => None
+    }
+}
+
+pub fn header_parse<T: FromStr>(headers: &Headers, name: &str)
+    -> Result<Option<T>, SwiftError>
+{
+    match header_string(headers, name) {
+        Some(s) => match s.trim().parse::<T>() {
+            Ok(v) => Ok(Some(v)),
+            Err(_) => {
+                let err_msg = format!("Invalid value for {}: {}", name, s);
+                Err(SwiftError::Parse(err_msg))
+            }
+        },
+        None => Ok(None)
+    }
+}
+
+// e.g. the Last-Modified header
+pub fn parse_http_date(s: &str) -> Option<DateTime<UTC>> {
+    match DateTime::parse_from_rfc2822(s) {
+        Ok(d) => Some(d.with_timezone(&UTC)),
+        Err(_) => None
+    }
+}
+
+// Swift listings give times in UTC, without a timezone
+pub fn parse_listing_time(s: &str) -> Option<DateTime<UTC>> {
+    match NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f") {
+        Ok(naive) => Some(DateTime::from_utc(naive, UTC)),
+        Err(_) => None
+    }
+}
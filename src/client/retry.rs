@@ -0,0 +1,59 @@
+use reqwest::StatusCode;
+use std::cmp;
+use std::thread;
+use std::time::Duration;
+
+use client::errors::SwiftError;
+
+/*
+ * Retrying requests that failed for reasons that may not happen again
+ */
+
+// Doubled after each failed attempt
+const RETRY_BACKOFF_MS: u64 = 1000;
+const MAX_RETRY_BACKOFF_MS: u64 = 30000;
+
+/*
+ * Transport errors, server errors, timeouts, rate limiting and corrupted
+ * uploads (422) are worth retrying. Other client errors (a missing
+ * container, a failed If-Match and so on) will only happen again.
+ */
+pub fn is_retryable(err: &SwiftError) -> bool {
+    match *err {
+        SwiftError::Http(_) => true,
+        SwiftError::Io(_) => true,
+        SwiftError::ChecksumMismatch(_) => true,
+        SwiftError::Status(ref status, _) => {
+            status.is_server_error() ||
+                *status == StatusCode::RequestTimeout ||
+                *status == StatusCode::TooManyRequests
+        },
+        _ => false
+    }
+}
+
+/*
+ * Call attempt until it succeeds, fails with an error that isn't worth
+ * retrying, or has been retried the given number of times
+ */
+pub fn with_retries<T, F>(retries: u32, description: &str, mut attempt: F)
+    -> Result<T, SwiftError>
+    where F: FnMut() -> Result<T, SwiftError>
+{
+    let mut failures = 0;
+    loop {
+        match attempt() {
+            Ok(r) => return Ok(r),
+            Err(e) => {
+                warn!("{} failed: {}", description, e);
+                if failures >= retries || !is_retryable(&e) {
+                    return Err(e)
+                }
+            }
+        };
+        let backoff = cmp::min(
+            RETRY_BACKOFF_MS << cmp::min(failures, 16), MAX_RETRY_BACKOFF_MS);
+        thread::sleep(Duration::from_millis(backoff));
+        failures += 1;
+    }
+}
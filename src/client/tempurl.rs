@@ -0,0 +1,312 @@
+use chrono::{DateTime, Duration, UTC};
+use crypto::hmac::Hmac;
+use crypto::mac::Mac;
+use crypto::sha1::Sha1;
+use crypto::sha2::{Sha256, Sha512};
+use rustc_serialize::hex::ToHex;
+use std::sync::Arc;
+use url::Url;
+use url::form_urlencoded;
+use url::percent_encoding::percent_decode;
+
+use auth::sessions::Auth;
+use client::errors::SwiftError;
+use client::metadata::{MetadataKind, raw_metadata_header};
+use client::request::{HeadAccount, HeadContainer, RunSwiftRequest, encode_path};
+use client::response::check_status;
+
+/*
+ * Temporary URLs, signed with the account or container temp URL key
+ */
+
+// The metadata the keys are kept in, with a second one for rotating keys
+pub const TEMP_URL_KEY: &'static str = "Temp-URL-Key";
+pub const TEMP_URL_KEY_2: &'static str = "Temp-URL-Key-2";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TempUrlDigest {
+    Sha1,
+    Sha256,
+    Sha512
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TempUrlMethod {
+    Get,
+    Head,
+    Put
+}
+
+impl TempUrlMethod {
+    fn as_str(&self) -> &'static str {
+        match *self {
+            TempUrlMethod::Get => "GET",
+            TempUrlMethod::Head => "HEAD",
+            TempUrlMethod::Put => "PUT"
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TempUrlExpiry {
+    At(DateTime<UTC>),
+    // Relative to when the URL is generated
+    In(Duration)
+}
+
+impl TempUrlExpiry {
+    pub fn timestamp(&self) -> i64 {
+        match *self {
+            TempUrlExpiry::At(t) => t.timestamp(),
+            TempUrlExpiry::In(d) => (UTC::now() + d).timestamp()
+        }
+    }
+}
+
+pub struct TempUrl<A> {
+    container: String,
+    // The object, or the prefix for prefix-based URLs
+    object: String,
+    key: String,
+    prefix: bool,
+    prefixed_object: Option<String>,
+    method: TempUrlMethod,
+    expiry: TempUrlExpiry,
+    digest: TempUrlDigest,
+    ip_range: Option<String>,
+    auth: Arc<A>
+}
+
+impl<AS: Sized+Auth> TempUrl<AS> {
+    pub fn new(
+        auth: Arc<AS>, container: String, object: String, key: String
+    ) -> TempUrl<AS> {
+        TempUrl {
+            container: container,
+            object: object,
+            key: key,
+            prefix: false,
+            prefixed_object: None,
+            method: TempUrlMethod::Get,
+            expiry: TempUrlExpiry::In(Duration::hours(1)),
+            digest: TempUrlDigest::Sha256,
+            ip_range: None,
+            auth: auth
+        }
+    }
+
+    // Any object under the prefix can be fetched with the same signature
+    pub fn new_prefix(
+        auth: Arc<AS>, container: String, prefix: String, key: String
+    ) -> TempUrl<AS> {
+        let mut temp_url = TempUrl::new(auth, container, prefix, key);
+        temp_url.prefix = true;
+        temp_url
+    }
+
+    /*
+     * For prefix-based URLs, the object under the prefix that the URL
+     * should point at. The same query string works for any of them.
+     */
+    pub fn object<S: Into<String>>(mut self, object: S) -> TempUrl<AS> {
+        self.prefixed_object = Some(object.into());
+        self
+    }
+
+    pub fn method(mut self, method: TempUrlMethod) -> TempUrl<AS> {
+        self.method = method;
+        self
+    }
+
+    pub fn expires_at(mut self, expires: DateTime<UTC>) -> TempUrl<AS> {
+        self.expiry = TempUrlExpiry::At(expires);
+        self
+    }
+
+    pub fn expires_in(mut self, duration: Duration) -> TempUrl<AS> {
+        self.expiry = TempUrlExpiry::In(duration);
+        self
+    }
+
+    pub fn digest(mut self, digest: TempUrlDigest) -> TempUrl<AS> {
+        self.digest = digest;
+        self
+    }
+
+    // e.g. "203.0.113.0/24", or a single address
+    pub fn ip_range<S: Into<String>>(mut self, ip_range: S) -> TempUrl<AS> {
+        self.ip_range = Some(ip_range.into());
+        self
+    }
+
+    pub fn url(self) -> Result<String, SwiftError> {
+        let (storage_url, storage_path) = try!(storage_url_path(self.auth.as_ref()));
+
+        let path = format!("{}/{}/{}", storage_path, self.container, self.object);
+        let expires = self.expiry.timestamp();
+        let signed_path = if self.prefix {
+            format!("prefix:{}", path)
+        } else {
+            path
+        };
+        let signature = temp_url_signature(
+            &self.key, self.digest, self.method, expires, &signed_path,
+            &self.ip_range);
+
+        let mut query = form_urlencoded::Serializer::new(String::new());
+        query.append_pair("temp_url_sig", &signature);
+        query.append_pair("temp_url_expires", &format!("{}", expires));
+        if self.prefix {
+            query.append_pair("temp_url_prefix", &self.object);
+        }
+        if let Some(ref ip_range) = self.ip_range {
+            query.append_pair("temp_url_ip_range", ip_range);
+        }
+        let object = match self.prefixed_object {
+            Some(ref o) if self.prefix => o,
+            _ => &self.object
+        };
+        // Quoted as Swift will unquote it, before it checks the signature
+        Ok(format!("{}/{}?{}",
+                   storage_url,
+                   encode_path(&format!("{}/{}", self.container, object)),
+                   query.finish()))
+    }
+}
+
+/*
+ * The container's temp URL key, falling back to the account's. The raw
+ * header is read, as Swift signs with the key exactly as it's stored.
+ */
+pub fn find_temp_url_key<AS: Sized+Auth>(auth: &Arc<AS>, container: &str)
+    -> Result<String, SwiftError>
+{
+    let resp = try!(check_status(try!(
+        HeadContainer::new(auth.clone(), String::from(container)).run_request())));
+    if let Some(key) = raw_metadata_header(resp.headers(), MetadataKind::Container,
+                                           TEMP_URL_KEY) {
+        return Ok(key)
+    }
+    let resp = try!(check_status(try!(HeadAccount::new(auth.clone()).run_request())));
+    match raw_metadata_header(resp.headers(), MetadataKind::Account, TEMP_URL_KEY) {
+        Some(key) => Ok(key),
+        None => {
+            let err_msg = format!("No temp URL key set for {} or its account", container);
+            Err(SwiftError::NotFound(err_msg))
+        }
+    }
+}
+
+/*
+ * The storage URL from the auth session, and its unquoted path, which
+ * is what signatures are made from
+ */
+pub fn storage_url_path(auth: &Auth) -> Result<(String, String), SwiftError> {
+    let info = try!(auth.session_info().map_err(SwiftError::Auth));
+    let storage_url = String::from(info.storage_url().trim_right_matches('/'));
+    match Url::parse(&storage_url) {
+        Ok(u) => {
+            let path = percent_decode(u.path().as_bytes()).decode_utf8_lossy().into_owned();
+            Ok((storage_url, path))
+        },
+        Err(e) => {
+            let err_msg = format!("Invalid storage URL {}: {}", storage_url, e);
+            Err(SwiftError::Parse(err_msg))
+        }
+    }
+}
+
+/*
+ * The signature is the HMAC of the method, expiry and path, e.g.
+ *   "GET\n1700000000\n/v1/AUTH_account/container/object"
+ * prefixed with the IP range when there is one
+ */
+pub fn temp_url_signature(
+    key: &str, digest: TempUrlDigest, method: TempUrlMethod, expires: i64,
+    path: &str, ip_range: &Option<String>
+) -> String {
+    let mut body = String::new();
+    if let Some(ref ip_range) = *ip_range {
+        body.push_str(&format!("ip={}\n", ip_range));
+    }
+    body.push_str(&format!("{}\n{}\n{}", method.as_str(), expires, path));
+    hmac_hex(digest, key, &body)
+}
+
+// Swift tells the digests apart by the length of the hex signature
+pub fn hmac_hex(digest: TempUrlDigest, key: &str, message: &str) -> String {
+    let key = key.as_bytes();
+    let message = message.as_bytes();
+    let code = match digest {
+        TempUrlDigest::Sha1 => {
+            let mut hmac = Hmac::new(Sha1::new(), key);
+            hmac.input(message);
+            hmac.result().code().to_vec()
+        },
+        TempUrlDigest::Sha256 => {
+            let mut hmac = Hmac::new(Sha256::new(), key);
+            hmac.input(message);
+            hmac.result().code().to_vec()
+        },
+        TempUrlDigest::Sha512 => {
+            let mut hmac = Hmac::new(Sha512::new(), key);
+            hmac.input(message);
+            hmac.result().code().to_vec()
+        }
+    };
+    code.to_hex()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, UTC};
+    use std::sync::Arc;
+
+    use auth::sessions::StaticTokenAuth;
+    use client::tempurl::{
+        TempUrl, TempUrlDigest, TempUrlMethod, hmac_hex, temp_url_signature
+    };
+
+    const PATH: &'static str = "/v1/AUTH_account/container/object";
+
+    #[test]
+    fn hmac_sha1_known_answer() {
+        // From RFC 2202
+        assert_eq!(hmac_hex(TempUrlDigest::Sha1, "Jefe", "what do ya want for nothing?"),
+                   "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79");
+    }
+
+    // The key, expiry and path from the Swift tempurl documentation
+    #[test]
+    fn signature_known_answer() {
+        assert_eq!(temp_url_signature("mykey", TempUrlDigest::Sha1, TempUrlMethod::Get,
+                                      1323479485, PATH, &None),
+                   "d9fc2067e52b06598421664cf6610bfc8fc431f6");
+        assert_eq!(temp_url_signature("mykey", TempUrlDigest::Sha256, TempUrlMethod::Get,
+                                      1323479485, PATH, &None),
+                   "05cb4ea08a08f2fdaef35d0f344975370077835c23bdc9342099ecbf03bc0378");
+    }
+
+    #[test]
+    fn signature_with_ip_range() {
+        let ip_range = Some(String::from("1.2.3.4"));
+        assert_eq!(temp_url_signature("mykey", TempUrlDigest::Sha1, TempUrlMethod::Get,
+                                      1323479485, PATH, &ip_range),
+                   "67127dd122b4b2fbd21796f253813b5a211d84ab");
+    }
+
+    #[test]
+    fn url_quotes_names() {
+        let auth = Arc::new(StaticTokenAuth::new(
+            String::from("token"), String::from("https://swift.example.com/v1/AUTH_account")));
+        let url = TempUrl::new(auth, String::from("c"), String::from("dir/100%41 x"),
+                               String::from("mykey"))
+            .digest(TempUrlDigest::Sha1)
+            .expires_at(UTC.timestamp(1323479485, 0))
+            .url().unwrap();
+        // Signed with the unquoted path
+        assert_eq!(url, "https://swift.example.com/v1/AUTH_account/c/dir/100%2541%20x\
+                         ?temp_url_sig=9f5e89304f460424057ad176bad52e3cd99224d4\
+                         &temp_url_expires=1323479485");
+    }
+}
@@ -1,9 +1,11 @@
 extern crate chrono;
+extern crate crypto;
 #[macro_use] extern crate hyper;
 #[macro_use] extern crate log;
 extern crate reqwest;
 extern crate rustc_serialize;
-extern crate url;
+#[macro_use] extern crate url;
+extern crate xml;
 
 pub mod auth;
 pub mod client;
@@ -1,16 +1,29 @@
 #[macro_use]
 extern crate lazy_static;
+extern crate chrono;
 extern crate hyper;
+extern crate reqwest;
 extern crate rust_swiftclient;
 
 use std::env;
+use std::fs::{self, File};
 use std::io;
+use std::io::Read;
 use std::sync::Arc;
 
-use rust_swiftclient::auth::sessions::KeystoneAuthV2;
+use chrono::{Duration, UTC};
+use hyper::header::{ByteRangeSpec, ContentType};
+use rust_swiftclient::auth::sessions::{Interface, KeystoneAuthV2};
+use rust_swiftclient::client::errors::SwiftError;
+use rust_swiftclient::client::listing::ObjectEntry;
+use rust_swiftclient::client::metadata::Metadata;
+use rust_swiftclient::client::object::ObjectManifest;
 use rust_swiftclient::client::request::{
-    RunSwiftRequest, SwiftConnection
+    CopyMethod, GetObjectResult, PutContainerResult, RunSwiftRequest,
+    RunTypedSwiftRequest, SwiftConnection, SwiftRequestHeaders
 };
+use rust_swiftclient::client::response::check_status;
+use rust_swiftclient::client::tempurl::TempUrlDigest;
 
 fn get_arg(os_var: String) -> String {
     match env::var(&os_var) {
@@ -30,12 +43,53 @@ lazy_static! {
                     get_arg(String::from("OS_PASSWORD")),
                     get_arg(String::from("OS_PROJECT_NAME")),
                     get_arg(String::from("OS_AUTH_URL")),
-                    None
+                    None,
+                    Interface::Public
                 )
             )
         );
 }
 
+#[test]
+fn session_info() {
+    let sw = SWIFT.clone();
+    match sw.session_info() {
+        Ok(info) => {
+            assert!(info.storage_url().starts_with("http"));
+            assert!(info.expires().is_some());
+            let stores = info.catalog().services_of_type("object-store");
+            assert!(stores.iter().any(|s| {
+                s.endpoints().iter().any(|e| e.url() == info.storage_url())
+            }));
+        },
+        Err(s) => panic!("{}", s)
+    };
+}
+
+#[test]
+fn get_info() {
+    let sw = SWIFT.clone();
+    let caps = match sw.get_info().run_typed_request() {
+        Ok(caps) => caps,
+        Err(s) => panic!("{}", s)
+    };
+    assert!(caps.swift.max_file_size.is_some());
+    assert!(caps.has("swift"));
+    // Uploads pick their limits from the capabilities
+    let container = CONTAINER.clone();
+    let object = format!("{}-info-slo", *OBJECT);
+    match sw.slo_upload(container.clone(), object.clone(), &b"capabilities"[..])
+            .capabilities(&caps).run_typed_request() {
+        Ok(upload) => assert_eq!(upload.segments.len(), 1),
+        Err(s) => panic!("{}", s)
+    };
+    match sw.delete_object(container.clone(), object)
+            .multipart_manifest_delete(true).run_typed_request() {
+        Ok(()) => {},
+        Err(s) => panic!("{}", s)
+    };
+}
+
 #[test]
 fn head_account() {
     let sw = SWIFT.clone();
@@ -92,6 +146,523 @@ fn get_container() {
     };
 }
 
+#[test]
+fn get_container_listing() {
+    let sw = SWIFT.clone();
+    // A container of its own, since other tests add and remove objects
+    let container = format!("{}-listing", *CONTAINER);
+    let objects = vec![String::from("a"), String::from("dir/b")];
+    match sw.put_container(container.clone()).run_typed_request() {
+        Ok(_) => {},
+        Err(s) => panic!("{}", s)
+    };
+    for object in &objects {
+        match sw.put_object(container.clone(), object.clone(), "listing")
+                .run_request().and_then(check_status) {
+            Ok(_) => {},
+            Err(s) => panic!("{}", s)
+        };
+    }
+    match sw.get_container(container.clone()).run_typed_request() {
+        Ok(listing) => {
+            assert_eq!(listing.stats.object_count as usize, objects.len());
+            let names: Vec<&str> = listing.objects.iter().map(|o| o.name()).collect();
+            assert_eq!(names, vec!["a", "dir/b"]);
+        },
+        Err(s) => panic!("{}", s)
+    };
+    match sw.get_container(container.clone()).delimiter("/").run_typed_request() {
+        Ok(listing) => {
+            assert_eq!(listing.objects.len(), 2);
+            assert_eq!(listing.objects[1], ObjectEntry::Subdir(String::from("dir/")));
+        },
+        Err(s) => panic!("{}", s)
+    };
+    for object in objects {
+        match sw.delete_object(container.clone(), object).run_typed_request() {
+            Ok(()) => {},
+            Err(s) => panic!("{}", s)
+        };
+    }
+    match sw.delete_container(container).run_typed_request() {
+        Ok(_) => {},
+        Err(s) => panic!("{}", s)
+    };
+}
+
+#[test]
+fn iter_container() {
+    let sw = SWIFT.clone();
+    let container = CONTAINER.clone();
+    let object = OBJECT.clone();
+    // Use tiny pages, so the marker has to be followed
+    let names: Vec<String> = sw.iter_container(container).page_size(1).map(|o| {
+        match o {
+            Ok(o) => String::from(o.name()),
+            Err(s) => panic!("{}", s)
+        }
+    }).collect();
+    assert!(names.contains(&object));
+    let mut sorted = names.clone();
+    sorted.sort();
+    sorted.dedup();
+    assert_eq!(names, sorted);
+}
+
+#[test]
+fn container_lifecycle() {
+    let sw = SWIFT.clone();
+    let container = format!("{}-lifecycle", *CONTAINER);
+    match sw.put_container(container.clone()).metadata("Test", "one")
+            .run_typed_request() {
+        Ok(r) => assert_eq!(r, PutContainerResult::Created),
+        Err(s) => panic!("{}", s)
+    };
+    match sw.head_container(container.clone()).run_typed_request() {
+        Ok(stats) => assert_eq!(stats.object_count, 0),
+        Err(s) => panic!("{}", s)
+    };
+    match sw.post_container(container.clone()).metadata("Test", "two")
+            .run_typed_request() {
+        Ok(()) => {},
+        Err(s) => panic!("{}", s)
+    };
+    match sw.delete_container(container.clone()).run_typed_request() {
+        Ok(()) => {},
+        Err(s) => panic!("{}", s)
+    };
+    match sw.head_container(container.clone()).run_typed_request() {
+        Err(SwiftError::NotFound(_)) => {},
+        Ok(_) => panic!("Container still exists: {}", container),
+        Err(s) => panic!("{}", s)
+    };
+}
+
+#[test]
+fn object_lifecycle() {
+    let sw = SWIFT.clone();
+    let container = CONTAINER.clone();
+    let object = format!("{}-lifecycle", *OBJECT);
+    let body = String::from("object lifecycle");
+    match sw.put_object(container.clone(), object.clone(), body.clone())
+            .run_request() {
+        Ok(resp) => assert!(resp.status().is_success()),
+        Err(s) => panic!("{}", s)
+    };
+    match sw.post_object(container.clone(), object.clone())
+            .metadata("Colour", "blue").run_typed_request() {
+        Ok(()) => {},
+        Err(s) => panic!("{}", s)
+    };
+    match sw.head_object(container.clone(), object.clone()).run_typed_request() {
+        Ok(meta) => {
+            assert_eq!(meta.content_length, body.len() as u64);
+            assert_eq!(meta.metadata.get("colour"), Some(&String::from("blue")));
+        },
+        Err(s) => panic!("{}", s)
+    };
+    match sw.delete_object(container.clone(), object.clone()).run_typed_request() {
+        Ok(()) => {},
+        Err(s) => panic!("{}", s)
+    };
+    match sw.head_object(container.clone(), object.clone()).run_typed_request() {
+        Err(SwiftError::NotFound(_)) => {},
+        Ok(_) => panic!("Object still exists: {}", object),
+        Err(s) => panic!("{}", s)
+    };
+}
+
+#[test]
+fn metadata_round_trip() {
+    let sw = SWIFT.clone();
+    let container = CONTAINER.clone();
+    let object = format!("{}-metadata", *OBJECT);
+    let mut meta = Metadata::new();
+    meta.insert("Colour", "blue");
+    meta.insert("Name", "caf\u{e9}");
+    match sw.put_object(container.clone(), object.clone(), "metadata")
+            .metadata_map(&meta).run_request() {
+        Ok(resp) => assert!(resp.status().is_success()),
+        Err(s) => panic!("{}", s)
+    };
+    match sw.head_object(container.clone(), object.clone()).run_typed_request() {
+        Ok(head) => assert_eq!(head.metadata, meta),
+        Err(s) => panic!("{}", s)
+    };
+    match sw.post_container(container.clone())
+            .metadata_map(&meta).run_typed_request() {
+        Ok(()) => {},
+        Err(s) => panic!("{}", s)
+    };
+    match sw.head_container(container.clone()).run_typed_request() {
+        Ok(stats) => {
+            assert_eq!(stats.metadata.get("colour"), Some(&String::from("blue")));
+            assert_eq!(stats.metadata.get("NAME"), meta.get("name"));
+        },
+        Err(s) => panic!("{}", s)
+    };
+    match sw.post_container(container.clone())
+            .remove_metadata("Colour").remove_metadata("Name")
+            .run_typed_request() {
+        Ok(()) => {},
+        Err(s) => panic!("{}", s)
+    };
+    match sw.delete_object(container.clone(), object).run_typed_request() {
+        Ok(()) => {},
+        Err(s) => panic!("{}", s)
+    };
+}
+
+#[test]
+fn copy_object() {
+    let sw = SWIFT.clone();
+    let container = CONTAINER.clone();
+    // Names that need quoting in the copy headers
+    let source = format!("{}-copy source?%41", *OBJECT);
+    let body = String::from("copy source");
+    match sw.put_object(container.clone(), source.clone(), body.clone())
+            .run_request() {
+        Ok(resp) => assert!(resp.status().is_success()),
+        Err(s) => panic!("{}", s)
+    };
+    for (i, method) in vec![CopyMethod::Copy, CopyMethod::PutCopyFrom]
+            .into_iter().enumerate() {
+        let dest = format!("{}-copy dest?%41-{}", *OBJECT, i);
+        match sw.copy_object(container.clone(), source.clone(),
+                             container.clone(), dest.clone())
+                .method(method).metadata("Copied", "yes").run_typed_request() {
+            Ok(result) => assert!(result.etag.is_some()),
+            Err(s) => panic!("{}", s)
+        };
+        match sw.head_object(container.clone(), dest.clone()).run_typed_request() {
+            Ok(meta) => {
+                assert_eq!(meta.content_length, body.len() as u64);
+                assert_eq!(meta.metadata.get("copied"), Some(&String::from("yes")));
+            },
+            Err(s) => panic!("{}", s)
+        };
+        match sw.delete_object(container.clone(), dest).run_typed_request() {
+            Ok(()) => {},
+            Err(s) => panic!("{}", s)
+        };
+    }
+    match sw.delete_object(container.clone(), source).run_typed_request() {
+        Ok(()) => {},
+        Err(s) => panic!("{}", s)
+    };
+}
+
+#[test]
+fn slo_upload() {
+    let sw = SWIFT.clone();
+    let container = CONTAINER.clone();
+    let object = format!("{}-slo", *OBJECT);
+    let body = b"static large object body".to_vec();
+    let upload = match sw.slo_upload(container.clone(), object.clone(), &body[..])
+            .segment_size(10).concurrency(2).run_typed_request() {
+        Ok(upload) => upload,
+        Err(s) => panic!("{}", s)
+    };
+    assert_eq!(upload.segments.len(), 3);
+    // Segments uploaded in parallel must still be listed in order
+    assert_eq!(upload.segments[0].size_bytes, 10);
+    assert_eq!(upload.segments[2].size_bytes, 4);
+    match sw.head_object(container.clone(), object.clone()).run_typed_request() {
+        Ok(meta) => assert_eq!(meta.content_length, body.len() as u64),
+        Err(s) => panic!("{}", s)
+    };
+    match sw.delete_object(container.clone(), object)
+            .multipart_manifest_delete(true).run_typed_request() {
+        Ok(()) => {},
+        Err(s) => panic!("{}", s)
+    };
+}
+
+#[test]
+fn dlo_upload() {
+    let sw = SWIFT.clone();
+    let container = CONTAINER.clone();
+    let object = format!("{}-dlo", *OBJECT);
+    let body = b"dynamic large object body".to_vec();
+    let upload = match sw.dlo_upload(container.clone(), object.clone(), &body[..])
+            .segment_size(10).run_typed_request() {
+        Ok(upload) => upload,
+        Err(s) => panic!("{}", s)
+    };
+    assert_eq!(upload.segments.len(), 3);
+    match sw.head_object(container.clone(), object.clone()).run_typed_request() {
+        Ok(meta) => {
+            assert_eq!(meta.content_length, body.len() as u64);
+            match meta.manifest {
+                Some(ObjectManifest::Dynamic { container: c, .. }) =>
+                    assert_eq!(c, format!("{}_segments", container)),
+                other => panic!("Not a DLO manifest: {:?}", other)
+            };
+        },
+        Err(s) => panic!("{}", s)
+    };
+    match sw.delete_object(container.clone(), object).run_typed_request() {
+        Ok(()) => {},
+        Err(s) => panic!("{}", s)
+    };
+    for segment in upload.segments {
+        let mut parts = segment.path.splitn(2, '/');
+        let c = String::from(parts.next().unwrap());
+        let o = String::from(parts.next().unwrap());
+        match sw.delete_object(c, o).run_typed_request() {
+            Ok(()) => {},
+            Err(s) => panic!("{}", s)
+        };
+    }
+}
+
+#[test]
+fn get_object_ranges() {
+    let sw = SWIFT.clone();
+    let container = CONTAINER.clone();
+    let object = format!("{}-ranges", *OBJECT);
+    match sw.put_object(container.clone(), object.clone(), "0123456789")
+            .run_request() {
+        Ok(resp) => assert!(resp.status().is_success()),
+        Err(s) => panic!("{}", s)
+    };
+    match sw.get_object(container.clone(), object.clone())
+            .range(ByteRangeSpec::FromTo(2, 4)).run_typed_request() {
+        Ok(GetObjectResult::Partial(_, range, mut resp)) => {
+            assert_eq!((range.start, range.end, range.total), (2, 4, Some(10)));
+            let mut body = String::new();
+            resp.read_to_string(&mut body).unwrap();
+            assert_eq!(body, "234");
+        },
+        Ok(other) => panic!("Expected partial content: {:?}", other),
+        Err(s) => panic!("{}", s)
+    };
+    match sw.get_object(container.clone(), object.clone())
+            .range(ByteRangeSpec::FromTo(0, 1))
+            .range(ByteRangeSpec::Last(2)).run_typed_request() {
+        Ok(GetObjectResult::Parts(_, parts)) => {
+            assert_eq!(parts.len(), 2);
+            assert_eq!(parts[0].data, b"01".to_vec());
+            assert_eq!(parts[1].data, b"89".to_vec());
+        },
+        Ok(other) => panic!("Expected multipart content: {:?}", other),
+        Err(s) => panic!("{}", s)
+    };
+    let etag = match sw.head_object(container.clone(), object.clone())
+            .run_typed_request() {
+        Ok(meta) => meta.etag.unwrap(),
+        Err(s) => panic!("{}", s)
+    };
+    match sw.get_object(container.clone(), object.clone())
+            .if_none_match(etag).run_typed_request() {
+        Ok(GetObjectResult::NotModified(_)) => {},
+        Ok(other) => panic!("Expected not modified: {:?}", other),
+        Err(s) => panic!("{}", s)
+    };
+    match sw.delete_object(container.clone(), object).run_typed_request() {
+        Ok(()) => {},
+        Err(s) => panic!("{}", s)
+    };
+}
+
+#[test]
+fn download_object() {
+    let sw = SWIFT.clone();
+    let container = CONTAINER.clone();
+    let plain = format!("{}-download", *OBJECT);
+    let slo = format!("{}-download-slo", *OBJECT);
+    let body = b"parallel ranged download body".to_vec();
+    match sw.put_object(container.clone(), plain.clone(), body.clone())
+            .run_request() {
+        Ok(resp) => assert!(resp.status().is_success()),
+        Err(s) => panic!("{}", s)
+    };
+    match sw.slo_upload(container.clone(), slo.clone(), &body[..])
+            .segment_size(8).run_typed_request() {
+        Ok(_) => {},
+        Err(s) => panic!("{}", s)
+    };
+    let path = env::temp_dir().join("rust-swiftclient-download");
+    for object in vec![plain.clone(), slo.clone()] {
+        match sw.download_object(container.clone(), object, &path)
+                .range_size(5).concurrency(3).run_typed_request() {
+            Ok(meta) => assert_eq!(meta.content_length, body.len() as u64),
+            Err(s) => panic!("{}", s)
+        };
+        let mut downloaded = Vec::new();
+        File::open(&path).unwrap().read_to_end(&mut downloaded).unwrap();
+        assert_eq!(downloaded, body);
+    }
+    let _ = fs::remove_file(&path);
+    match sw.delete_object(container.clone(), plain).run_typed_request() {
+        Ok(()) => {},
+        Err(s) => panic!("{}", s)
+    };
+    match sw.delete_object(container.clone(), slo)
+            .multipart_manifest_delete(true).run_typed_request() {
+        Ok(()) => {},
+        Err(s) => panic!("{}", s)
+    };
+}
+
+#[test]
+fn checksums() {
+    let sw = SWIFT.clone();
+    let container = CONTAINER.clone();
+    let object = format!("{}-checksum", *OBJECT);
+    let body = b"checksummed body".to_vec();
+    match sw.put_object(container.clone(), object.clone(), body.clone())
+            .checksum().run_request() {
+        Ok(resp) => assert!(resp.status().is_success()),
+        Err(s) => panic!("{}", s)
+    };
+    match sw.put_object_stream(container.clone(), object.clone(),
+                               io::Cursor::new(body.clone())).run_request() {
+        Ok(resp) => assert!(resp.status().is_success()),
+        Err(s) => panic!("{}", s)
+    };
+    let mut downloaded = Vec::new();
+    match sw.get_object(container.clone(), object.clone())
+            .verify_md5(true).write_to(&mut downloaded) {
+        Ok(_) => assert_eq!(downloaded, body),
+        Err(s) => panic!("{}", s)
+    };
+    // Swift should reject data that doesn't match the ETag sent with it
+    match sw.put_object(container.clone(), object.clone(), body.clone())
+            .raw_header("ETag", "00000000000000000000000000000000")
+            .run_request().and_then(check_status) {
+        Err(SwiftError::ChecksumMismatch(_)) => {},
+        Ok(resp) => panic!("Mismatched upload accepted: {}", resp.status()),
+        Err(s) => panic!("{}", s)
+    };
+    match sw.delete_object(container.clone(), object).run_typed_request() {
+        Ok(()) => {},
+        Err(s) => panic!("{}", s)
+    };
+}
+
+#[test]
+fn temp_url() {
+    let sw = SWIFT.clone();
+    let container = CONTAINER.clone();
+    let object = format!("{}-tempurl", *OBJECT);
+    let key = String::from("rust-swiftclient-test-key");
+    match sw.post_container(container.clone())
+            .temp_url_key(key.clone()).run_typed_request() {
+        Ok(()) => {},
+        Err(s) => panic!("{}", s)
+    };
+    match sw.put_object(container.clone(), object.clone(), "temp url body")
+            .run_request() {
+        Ok(resp) => assert!(resp.status().is_success()),
+        Err(s) => panic!("{}", s)
+    };
+    for digest in vec![TempUrlDigest::Sha1, TempUrlDigest::Sha256, TempUrlDigest::Sha512] {
+        let url = match sw.temp_url(container.clone(), object.clone(), key.clone())
+                .digest(digest).expires_in(Duration::minutes(5)).url() {
+            Ok(url) => url,
+            Err(s) => panic!("{}", s)
+        };
+        let mut resp = reqwest::get(&url[..]).unwrap();
+        assert!(resp.status().is_success());
+        let mut body = String::new();
+        resp.read_to_string(&mut body).unwrap();
+        assert_eq!(body, "temp url body");
+    }
+    let url = match sw.temp_url(container.clone(), object.clone(), key.clone())
+            .expires_at(UTC::now() - Duration::minutes(5)).url() {
+        Ok(url) => url,
+        Err(s) => panic!("{}", s)
+    };
+    assert!(!reqwest::get(&url[..]).unwrap().status().is_success());
+    match sw.delete_object(container.clone(), object).run_typed_request() {
+        Ok(()) => {},
+        Err(s) => panic!("{}", s)
+    };
+}
+
+#[test]
+fn form_post() {
+    let sw = SWIFT.clone();
+    let container = CONTAINER.clone();
+    let prefix = format!("{}-formpost/", *OBJECT);
+    match sw.post_container(container.clone())
+            .temp_url_key("rust-swiftclient-test-key").run_typed_request() {
+        Ok(()) => {},
+        Err(s) => panic!("{}", s)
+    };
+    // The key is looked up from the container
+    let form = match sw.form_post(container.clone(), prefix.clone())
+            .max_file_size(1024).max_file_count(1).fields() {
+        Ok(form) => form,
+        Err(s) => panic!("{}", s)
+    };
+    let boundary = "rust-swiftclient-boundary";
+    let mut body = String::new();
+    for (name, value) in form.fields() {
+        body.push_str(&format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+            boundary, name, value));
+    }
+    body.push_str(&format!(
+        "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"upload\"\r\n\
+         Content-Type: text/plain\r\n\r\nform post body\r\n--{}--\r\n",
+        boundary, boundary));
+    let client = reqwest::Client::new().unwrap();
+    let resp = client.post(&form.url[..])
+        .header(ContentType(format!("multipart/form-data; boundary={}", boundary)
+                            .parse().unwrap()))
+        .body(body).send().unwrap();
+    assert!(resp.status().is_success() || resp.status().is_redirection());
+    let object = format!("{}upload", prefix);
+    match sw.head_object(container.clone(), object.clone()).run_typed_request() {
+        Ok(meta) => assert_eq!(meta.content_length, 14),
+        Err(s) => panic!("{}", s)
+    };
+    match sw.delete_object(container.clone(), object).run_typed_request() {
+        Ok(()) => {},
+        Err(s) => panic!("{}", s)
+    };
+}
+
+#[test]
+fn bulk_delete() {
+    let sw = SWIFT.clone();
+    let container = CONTAINER.clone();
+    let objects: Vec<String> = vec!["plain", "with space", "100%", "a/b"].iter()
+        .map(|n| format!("{}-bulk/{}", *OBJECT, n)).collect();
+    for object in &objects {
+        match sw.put_object(container.clone(), object.clone(), "bulk")
+                .run_request().and_then(check_status) {
+            Ok(_) => {},
+            Err(s) => panic!("{}", s)
+        };
+    }
+    let mut paths: Vec<String> = objects.iter()
+        .map(|o| format!("{}/{}", container, o)).collect();
+    paths.push(format!("{}/{}-bulk/missing", container, *OBJECT));
+    // The test container isn't empty, so can't be deleted
+    paths.push(container.clone());
+    let result = match sw.bulk_delete(paths.clone())
+            .max_deletes_per_request(2).run_typed_request() {
+        Ok(result) => result,
+        Err(s) => panic!("{}", s)
+    };
+    assert_eq!(result.number_deleted, 4);
+    assert_eq!(result.number_not_found, 1);
+    assert_eq!(result.deleted, paths[..5].to_vec());
+    assert_eq!(result.failed.len(), 1);
+    assert_eq!(result.failed[0].path, container);
+    assert!(result.failed[0].status.starts_with("409"));
+    for object in objects {
+        match sw.head_object(container.clone(), object).run_typed_request() {
+            Err(SwiftError::NotFound(_)) => {},
+            Ok(_) => panic!("Object not deleted"),
+            Err(s) => panic!("{}", s)
+        };
+    }
+}
+
 #[test]
 fn get_object() {
     let sw = SWIFT.clone();